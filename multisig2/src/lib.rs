@@ -1,13 +1,16 @@
-use std::collections::HashSet;
-use std::convert::TryInto;
+use std::collections::{HashMap, HashSet};
+use std::convert::{TryFrom, TryInto};
+
+use ed25519_dalek::Verifier;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::{Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde_json::json;
 use near_sdk::{
-    env, near_bindgen, serde_json, AccountId, BorshStorageKey, Gas, PanicOnDefault, Promise,
-    PromiseOrValue, PublicKey,
+    env, ext_contract, near_bindgen, serde_json, AccountId, Balance, BorshStorageKey, Gas,
+    PanicOnDefault, Promise, PromiseOrValue, PromiseResult, PublicKey,
 };
 
 /// Unlimited allowance for multisig keys.
@@ -19,14 +22,74 @@ const REQUEST_COOLDOWN: u64 = 900_000_000_000;
 /// Default limit of active requests.
 const ACTIVE_REQUESTS_LIMIT: u32 = 12;
 
+/// Number of client nonces remembered per member for idempotent requests.
+const MAX_CLIENT_NONCES_PER_MEMBER: usize = 16;
+
+/// Maximum length of a client nonce.
+const MAX_CLIENT_NONCE_LEN: usize = 64;
+
+/// Number of recently executed request hashes remembered to reject replays.
+const MAX_EXECUTED_REQUEST_HASHES: usize = 32;
+
+/// Maximum number of expired requests pruned when a request is added, to bound gas.
+const MAX_AUTO_PRUNED_REQUESTS: usize = 4;
+
+/// Longest time to live of a request added with `add_request_with_ttl`: a year in nanoseconds.
+const MAX_REQUEST_TTL: u64 = 365 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Longest time confirmations count for with `SetConfirmationTtl`: a year in nanoseconds.
+const MAX_CONFIRMATION_TTL: u64 = 365 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Longest cooldown of new members set with `SetNewMemberCooldown`: a year in nanoseconds.
+const MAX_NEW_MEMBER_COOLDOWN: u64 = 365 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Maximum number of recipients of a `MultiTransfer`, to bound gas.
+const MAX_MULTI_TRANSFER_RECIPIENTS: usize = 32;
+
+/// Default limit of the code size of `DeployContract` actions.
+const DEFAULT_MAX_CONTRACT_CODE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Maximum length of a request tag.
+const MAX_TAG_LEN: usize = 32;
+
+/// Maximum length of the contract label.
+const MAX_LABEL_LEN: usize = 64;
+
+/// Maximum length of a note attached with `confirm_with_note`.
+const MAX_NOTE_LEN: usize = 128;
+
+/// Maximum number of notes attached to a request.
+const MAX_NOTES_PER_REQUEST: usize = 16;
+
+/// Deposit attached to token transfer calls, as required by the token standards.
+const ONE_YOCTO: Balance = 1;
+
+/// Default gas attached to calls made by helper actions like `FtTransfer` and `NftTransfer`.
+const DEFAULT_CALL_GAS: Gas = Gas(20_000_000_000_000);
+
+/// Gas attached to the callback that reports the result of request execution.
+const ON_EXECUTE_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Rough gas cost of the confirmation that executes a request, before its actions.
+const EXECUTION_BASE_GAS: Gas = Gas(10_000_000_000_000);
+
+/// Rough gas cost of executing an action that doesn't call another contract.
+const ACTION_GAS: Gas = Gas(5_000_000_000_000);
+
 /// Default set of methods that access key should have.
-const MULTISIG_METHOD_NAMES: &str = "add_request,delete_request,confirm,add_and_confirm_request";
+const MULTISIG_METHOD_NAMES: &str = "add_request,delete_request,confirm,add_and_confirm_request,\
+    confirm_as,add_request_idempotent,confirm_with_code,execute_ready,emit_state_snapshot,confirm_with_note,\
+    confirm_and_prune,add_request_with_ttl";
+
+/// Version of the contract code, taken from the crate manifest.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub type RequestId = u32;
+/// Ids are never reused, so they are wide enough to not run out for the life of the multisig.
+pub type RequestId = u64;
 
 /// Permissions for function call access key.
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
-#[cfg_attr(test, derive(PartialEq, Clone))]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
 #[serde(crate = "near_sdk::serde")]
 pub struct FunctionCallPermission {
     allowance: Option<U128>,
@@ -35,13 +98,20 @@ pub struct FunctionCallPermission {
 }
 
 /// Lowest level action that can be performed by the multisig contract.
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
-#[cfg_attr(test, derive(PartialEq, Clone))]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
 #[serde(tag = "type", crate = "near_sdk::serde")]
 pub enum MultiSigRequestAction {
+    // stored requests are encoded with the variant index, so new variants go last
     /// Transfers given amount to receiver.
-    Transfer { amount: U128 },
+    /// `memo` is only kept with the request for bookkeeping, NEAR transfers don't carry it.
+    Transfer {
+        amount: U128,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
     /// Create a new account.
+    /// Must be accompanied by a `Transfer`, `SweepTransfer` or `AddKey` in the same request.
     CreateAccount,
     /// Deploys contract to receiver's account. Can upgrade given contract as well.
     DeployContract { code: Base64VecU8 },
@@ -50,6 +120,7 @@ pub enum MultiSigRequestAction {
     /// Remove existing member of the multisig.
     DeleteMember { member: MultisigMember },
     /// Adds full access key to another account.
+    /// Function call keys for the multisig itself must list their method names.
     AddKey {
         public_key: PublicKey,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -70,15 +141,744 @@ pub enum MultiSigRequestAction {
     /// The REQUEST_COOLDOWN for requests is 15min
     /// Worst gas attack a malicious keyholder could do is 12 requests every 15min
     SetActiveRequestsLimit { active_requests_limit: u32 },
+    /// Transfers NFT owned by this account via `nft_transfer` on the NFT contract.
+    /// `contract_id` must be the request's receiver.
+    NftTransfer {
+        contract_id: AccountId,
+        token_id: String,
+        receiver_id: AccountId,
+        approval_id: Option<U64>,
+        memo: Option<String>,
+    },
+    /// Transfers fungible tokens owned by this account via `ft_transfer` on the token contract.
+    /// `token_id` must be the request's receiver.
+    FtTransfer {
+        token_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    },
+    /// Sets whether requests with the same receiver and actions as a pending one, memos aside,
+    /// are rejected.
+    SetRejectDuplicates { reject_duplicates: bool },
+    /// Removes all active requests originated by the member, keeping the member itself.
+    CancelMemberRequests { member: MultisigMember },
+    /// Raises the lowest id new requests can get. Can never be lowered.
+    SetRequestNonceFloor { request_nonce_floor: RequestId },
+    /// Sets gas attached to calls made by helper actions like `FtTransfer` and `NftTransfer`.
+    SetDefaultCallGas { default_call_gas: U64 },
+    /// Deploys contract committed to by its sha256 hash and size.
+    /// The code itself is supplied on execution via `confirm_with_code` or `execute_ready`.
+    DeployContractHash {
+        code_hash: Base64VecU8,
+        code_size: U64,
+    },
+    /// Sets methods of this contract that can't be called via `FunctionCall` on itself.
+    SetSelfCallDenylist { method_names: Vec<String> },
+    /// Removes confirmation entries that have no matching request and adds missing ones,
+    /// among `limit` request ids starting at `from_index`.
+    RepairInvariants { from_index: RequestId, limit: u32 },
+    /// Sets minimum time in nanoseconds between requests added by the same member. 0 disables it.
+    SetMinRequestInterval { min_request_interval: U64 },
+    /// Creates a committee whose `threshold` of members confirming counts as one confirmation.
+    /// Committee members must not be members of the multisig or of another committee.
+    CreateCommittee {
+        name: String,
+        members: Vec<MultisigMember>,
+        threshold: u32,
+    },
+    /// Removes the committee along with its progress on active requests.
+    DissolveCommittee { name: String },
+    /// Sets time in nanoseconds a confirmation counts for, at most a year. 0 means forever.
+    SetConfirmationTtl { confirmation_ttl: U64 },
+    /// Adds read-only member that can't add or confirm requests.
+    AddObserver { member: MultisigMember },
+    /// Removes existing observer.
+    RemoveObserver { member: MultisigMember },
+    /// Sets time in nanoseconds after being added during which a member can't confirm requests,
+    /// at most a year.
+    SetNewMemberCooldown { new_member_cooldown: U64 },
+    /// Transfers the whole balance except `reserve`, computed when the request is executed.
+    /// `receiver_id` must be the request's receiver.
+    SweepTransfer {
+        receiver_id: AccountId,
+        reserve: U128,
+    },
+    /// Replaces all members and observers with the given members and sets number of confirmations.
+    /// Removes all active requests. Can not be bundled with any other actions or transactions.
+    ReplaceAllMembers {
+        members: Vec<MultisigMember>,
+        num_confirmations: u32,
+    },
+    /// Sets whether requests whose execution failed are re-queued with no confirmations.
+    SetRetryFailedExecutions { retry_failed_executions: bool },
+    /// Sets the largest deposit a `FunctionCall` action can attach. `None` removes the cap.
+    SetMaxFunctionCallDeposit {
+        max_function_call_deposit: Option<U128>,
+    },
+    /// Sets the largest gas a `FunctionCall` action can attach. `None` removes the cap.
+    SetMaxFunctionCallGas { max_function_call_gas: Option<U64> },
+    /// Sets human readable label of the multisig. It has no effect on behavior.
+    SetLabel { label: Option<String> },
+    /// Sets whether requests identical to one executed within `replay_window` nanoseconds are rejected.
+    /// Only the latest 32 executed requests are remembered.
+    SetRejectReplays {
+        reject_replays: bool,
+        replay_window: U64,
+    },
+    /// Lets account member `account_id` move its membership to `new_account_id` with `migrate_my_account`.
+    ApproveAccountMigration {
+        account_id: AccountId,
+        new_account_id: AccountId,
+    },
+    /// Sets whether `SetActiveRequestsLimit` fails when the limit is below active requests
+    /// some member already has, instead of only logging an event.
+    SetStrictActiveRequestsLimit { strict_active_requests_limit: bool },
+    /// Sets whether requests can be confirmed before their `not_before` time.
+    /// Execution waits for `not_before` either way.
+    SetCollectEarlyConfirmations { collect_early_confirmations: bool },
+    /// Removes all members, committees and requests, deleting their access keys, and transfers
+    /// the balance not needed for storage to `beneficiary_id`, or the decommission beneficiary if set.
+    /// Can not be bundled with any other actions or transactions.
+    DecommissionContract { beneficiary_id: Option<AccountId> },
+    /// Sets the amount above which a `Transfer` action needs a non-empty `memo` justifying it.
+    /// `None` removes the requirement.
+    SetRequireMemoAbove { require_memo_above: Option<U128> },
+    /// Sets whether adding a request first deletes up to 4 requests that were added more than
+    /// `prune_after` nanoseconds ago. Requests are never pruned before the deletion cooldown passes.
+    SetAutoPrune { auto_prune: bool, prune_after: U64 },
+    /// Transfers given amounts to up to 32 accounts. Can not be bundled with any other actions
+    /// or transactions, and `receiver_id` must be the multisig itself.
+    MultiTransfer { transfers: Vec<(AccountId, U128)> },
+    /// Sets number of confirmations required to authorize requests as a percentage of voting members,
+    /// rounded up. `num_confirmations` is ignored while it's set, `None` goes back to it.
+    /// Can not be bundled with any other actions or transactions.
+    SetConfirmationPercentage { confirmation_percentage: Option<u8> },
+    /// Pauses or unpauses the multisig. While paused only requests to the multisig itself
+    /// can be added and executed. Unpausing resets the consecutive failed executions.
+    SetPaused { paused: bool },
+    /// Sets number of consecutive failed executions that pauses the multisig. 0 disables it.
+    SetFailurePauseThreshold { failure_pause_threshold: u32 },
+    /// Sets whether another member has to confirm `SetNumConfirmations`, `AddMember`,
+    /// `DeleteMember` and `AddKey` requests before the member who added them can.
+    SetRequireIndependentConfirmation {
+        require_independent_confirmation: bool,
+    },
+    /// Sets the limit of active requests of all members together. `None` removes the limit.
+    SetMaxTotalRequests { max_total_requests: Option<u32> },
+    /// Deletes and re-adds the access key of every access key member, restoring its allowance.
+    RefreshKeyAllowances,
+    /// Queues `request` on another multisig via its `add_request`.
+    /// `multisig_account` must be the request's receiver.
+    ProxyRequest {
+        multisig_account: AccountId,
+        request: ProxiedRequest,
+    },
+    /// Sets minimum time in nanoseconds between requests containing an action of given kind,
+    /// e.g. `"AddKey"`. `None` removes the cooldown.
+    SetActionKindCooldown { kind: String, cooldown: Option<U64> },
+    /// Sets the largest code size in bytes `DeployContract` and `DeployContractHash` actions can have.
+    /// Can not be bundled with any other actions or transactions.
+    SetMaxContractCodeSize { max_contract_code_size: U64 },
+    /// Restricts `member` to adding requests to `receivers`. Requests to the multisig itself
+    /// need it among them too. An empty list lifts the restriction.
+    /// Can not be bundled with any other actions or transactions.
+    SetMemberReceivers {
+        member: MultisigMember,
+        receivers: Vec<AccountId>,
+    },
+    /// Blocks executing actions that change members, committees or member weights, as well as
+    /// `migrate_my_account`, until `until` in nanoseconds. The lock can only be extended.
+    /// Can not be bundled with any other actions or transactions.
+    LockMembership { until: U64 },
+    /// Sets weight of the member's confirmations under the `CountAndWeight` quorum policy.
+    /// Members have weight 1 unless set. Can not be bundled with any other actions or transactions.
+    SetMemberWeight { member: MultisigMember, weight: u32 },
+    /// Sets whether requests need only enough confirmations, or also `weight_threshold` of
+    /// combined member weight. Can not be bundled with any other actions or transactions.
+    SetQuorumPolicy {
+        quorum_policy: QuorumPolicy,
+        weight_threshold: u32,
+    },
+    /// Remove existing access key member, same as `DeleteMember` with an `AccessKey` member.
+    DeleteMemberByKey { public_key: PublicKey },
+    /// Sets the account `DecommissionContract` transfers the balance to when it doesn't name one.
+    /// Can not be bundled with any other actions or transactions.
+    SetDecommissionBeneficiary { beneficiary_id: Option<AccountId> },
+}
+
+impl MultiSigRequestAction {
+    /// Copy of the action without its memo, which doesn't change what the action does.
+    fn without_memo(&self) -> Self {
+        let mut action = self.clone();
+        match &mut action {
+            MultiSigRequestAction::Transfer { memo, .. }
+            | MultiSigRequestAction::NftTransfer { memo, .. }
+            | MultiSigRequestAction::FtTransfer { memo, .. } => *memo = None,
+            _ => {}
+        }
+        action
+    }
+
+    /// Human readable description of the action sent to `receiver_id`.
+    fn describe(&self, receiver_id: &AccountId) -> String {
+        match self {
+            MultiSigRequestAction::Transfer { amount, memo } => match memo {
+                Some(memo) => format!("Transfer {} yⓃ to {} ({})", amount.0, receiver_id, memo),
+                None => format!("Transfer {} yⓃ to {}", amount.0, receiver_id),
+            },
+            MultiSigRequestAction::CreateAccount => format!("Create account {}", receiver_id),
+            MultiSigRequestAction::DeployContract { code } => {
+                format!("Deploy {} bytes of code to {}", code.0.len(), receiver_id)
+            }
+            MultiSigRequestAction::DeployContractHash {
+                code_hash,
+                code_size,
+            } => format!(
+                "Deploy {} bytes of code with sha256 {} to {}",
+                code_size.0,
+                hex_string(&code_hash.0),
+                receiver_id
+            ),
+            MultiSigRequestAction::AddMember { member } => {
+                format!("Add member {}", describe_member(member))
+            }
+            MultiSigRequestAction::DeleteMember { member } => {
+                format!("Delete member {}", describe_member(member))
+            }
+            MultiSigRequestAction::DeleteMemberByKey { public_key } => {
+                format!("Delete member key {}", String::from(public_key))
+            }
+            MultiSigRequestAction::AddKey {
+                public_key,
+                permission,
+            } => match permission {
+                Some(permission) => format!(
+                    "Add key {} for calling [{}] on {}",
+                    String::from(public_key),
+                    permission.method_names.join(", "),
+                    permission.receiver_id
+                ),
+                None => format!("Add full access key {}", String::from(public_key)),
+            },
+            MultiSigRequestAction::FunctionCall {
+                method_name,
+                deposit,
+                gas,
+                ..
+            } => format!(
+                "Call {} on {} with {} yⓃ deposit and {} gas",
+                method_name, receiver_id, deposit.0, gas.0
+            ),
+            MultiSigRequestAction::NftTransfer {
+                contract_id,
+                token_id,
+                receiver_id,
+                ..
+            } => format!(
+                "Transfer NFT {} of {} to {}",
+                token_id, contract_id, receiver_id
+            ),
+            MultiSigRequestAction::FtTransfer {
+                token_id,
+                receiver_id,
+                amount,
+                ..
+            } => format!(
+                "Transfer {} of token {} to {}",
+                amount.0, token_id, receiver_id
+            ),
+            MultiSigRequestAction::SweepTransfer {
+                receiver_id,
+                reserve,
+            } => format!("Transfer all but {} yⓃ to {}", reserve.0, receiver_id),
+            MultiSigRequestAction::ProxyRequest {
+                multisig_account,
+                request,
+            } => format!(
+                "Add request on {} to {}",
+                multisig_account, request.0.receiver_id
+            ),
+            MultiSigRequestAction::MultiTransfer { transfers } => format!(
+                "Transfer {}",
+                transfers
+                    .iter()
+                    .map(|(account_id, amount)| format!("{} yⓃ to {}", amount.0, account_id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            MultiSigRequestAction::SetNumConfirmations { num_confirmations } => {
+                format!("Set number of confirmations to {}", num_confirmations)
+            }
+            MultiSigRequestAction::SetConfirmationPercentage {
+                confirmation_percentage,
+            } => match confirmation_percentage {
+                Some(percentage) => format!("Set confirmations to {}% of members", percentage),
+                None => "Remove confirmation percentage".to_string(),
+            },
+            MultiSigRequestAction::SetPaused { paused } => format!("Set paused to {}", paused),
+            MultiSigRequestAction::SetRequireIndependentConfirmation {
+                require_independent_confirmation,
+            } => format!(
+                "Set require independent confirmation to {}",
+                require_independent_confirmation
+            ),
+            MultiSigRequestAction::SetFailurePauseThreshold {
+                failure_pause_threshold,
+            } => format!(
+                "Pause after {} consecutive failed executions",
+                failure_pause_threshold
+            ),
+            MultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit,
+            } => format!("Set active requests limit to {}", active_requests_limit),
+            MultiSigRequestAction::SetMaxTotalRequests { max_total_requests } => {
+                match max_total_requests {
+                    Some(max_total_requests) => {
+                        format!("Set total active requests limit to {}", max_total_requests)
+                    }
+                    None => "Remove total active requests limit".to_string(),
+                }
+            }
+            MultiSigRequestAction::CancelMemberRequests { member } => {
+                format!("Cancel requests of member {}", describe_member(member))
+            }
+            MultiSigRequestAction::SetRejectDuplicates { reject_duplicates } => {
+                format!("Set reject duplicates to {}", reject_duplicates)
+            }
+            MultiSigRequestAction::SetRequestNonceFloor {
+                request_nonce_floor,
+            } => format!("Set request nonce floor to {}", request_nonce_floor),
+            MultiSigRequestAction::SetDefaultCallGas { default_call_gas } => {
+                format!("Set default call gas to {}", default_call_gas.0)
+            }
+            MultiSigRequestAction::SetSelfCallDenylist { method_names } => {
+                format!("Set self call denylist to [{}]", method_names.join(", "))
+            }
+            MultiSigRequestAction::RepairInvariants { from_index, limit } => format!(
+                "Repair invariants of {} request ids from {}",
+                limit, from_index
+            ),
+            MultiSigRequestAction::RefreshKeyAllowances => {
+                "Refresh allowances of member keys".to_string()
+            }
+            MultiSigRequestAction::SetMinRequestInterval {
+                min_request_interval,
+            } => format!(
+                "Set minimum request interval to {} ns",
+                min_request_interval.0
+            ),
+            MultiSigRequestAction::SetActionKindCooldown { kind, cooldown } => match cooldown {
+                Some(cooldown) => format!("Set cooldown of {} requests to {} ns", kind, cooldown.0),
+                None => format!("Remove cooldown of {} requests", kind),
+            },
+            MultiSigRequestAction::CreateCommittee {
+                name,
+                members,
+                threshold,
+            } => format!(
+                "Create committee {} of {} out of [{}]",
+                name,
+                threshold,
+                members
+                    .iter()
+                    .map(describe_member)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            MultiSigRequestAction::DissolveCommittee { name } => {
+                format!("Dissolve committee {}", name)
+            }
+            MultiSigRequestAction::SetConfirmationTtl { confirmation_ttl } => {
+                format!("Set confirmation ttl to {} ns", confirmation_ttl.0)
+            }
+            MultiSigRequestAction::AddObserver { member } => {
+                format!("Add observer {}", describe_member(member))
+            }
+            MultiSigRequestAction::RemoveObserver { member } => {
+                format!("Remove observer {}", describe_member(member))
+            }
+            MultiSigRequestAction::SetNewMemberCooldown {
+                new_member_cooldown,
+            } => format!("Set new member cooldown to {} ns", new_member_cooldown.0),
+            MultiSigRequestAction::ReplaceAllMembers {
+                members,
+                num_confirmations,
+            } => format!(
+                "Replace all members with {} requiring {} confirmations",
+                members
+                    .iter()
+                    .map(describe_member)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                num_confirmations
+            ),
+            MultiSigRequestAction::LockMembership { until } => {
+                format!("Lock membership until {}", until.0)
+            }
+            MultiSigRequestAction::SetRetryFailedExecutions {
+                retry_failed_executions,
+            } => format!("Set retry failed executions to {}", retry_failed_executions),
+            MultiSigRequestAction::SetMaxFunctionCallDeposit {
+                max_function_call_deposit,
+            } => match max_function_call_deposit {
+                Some(deposit) => format!("Set max function call deposit to {} yocto", deposit.0),
+                None => "Remove max function call deposit".to_string(),
+            },
+            MultiSigRequestAction::SetMaxFunctionCallGas {
+                max_function_call_gas,
+            } => match max_function_call_gas {
+                Some(gas) => format!("Set max function call gas to {}", gas.0),
+                None => "Remove max function call gas".to_string(),
+            },
+            MultiSigRequestAction::SetLabel { label } => match label {
+                Some(label) => format!("Set label to {}", label),
+                None => "Remove label".to_string(),
+            },
+            MultiSigRequestAction::SetRejectReplays {
+                reject_replays,
+                replay_window,
+            } => format!(
+                "Set reject replays to {} with window of {} ns",
+                reject_replays, replay_window.0
+            ),
+            MultiSigRequestAction::ApproveAccountMigration {
+                account_id,
+                new_account_id,
+            } => format!(
+                "Approve migration of member {} to {}",
+                account_id, new_account_id
+            ),
+            MultiSigRequestAction::SetStrictActiveRequestsLimit {
+                strict_active_requests_limit,
+            } => format!(
+                "Set strict active requests limit to {}",
+                strict_active_requests_limit
+            ),
+            MultiSigRequestAction::SetCollectEarlyConfirmations {
+                collect_early_confirmations,
+            } => format!(
+                "Set collect early confirmations to {}",
+                collect_early_confirmations
+            ),
+            MultiSigRequestAction::DecommissionContract { beneficiary_id } => {
+                match beneficiary_id {
+                    Some(beneficiary_id) => format!(
+                        "Decommission the multisig and transfer its balance to {}",
+                        beneficiary_id
+                    ),
+                    None => "Decommission the multisig".to_string(),
+                }
+            }
+            MultiSigRequestAction::SetMemberWeight { member, weight } => format!(
+                "Set weight of member {} to {}",
+                describe_member(member),
+                weight
+            ),
+            MultiSigRequestAction::SetQuorumPolicy {
+                quorum_policy,
+                weight_threshold,
+            } => match quorum_policy {
+                QuorumPolicy::Count => "Require only the number of confirmations".to_string(),
+                QuorumPolicy::CountAndWeight => format!(
+                    "Require the number of confirmations and combined weight of {}",
+                    weight_threshold
+                ),
+            },
+            MultiSigRequestAction::SetMemberReceivers { member, receivers } => {
+                if receivers.is_empty() {
+                    format!(
+                        "Remove receiver restriction of member {}",
+                        describe_member(member)
+                    )
+                } else {
+                    format!(
+                        "Restrict member {} to receivers [{}]",
+                        describe_member(member),
+                        receivers
+                            .iter()
+                            .map(|receiver| receiver.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }
+            }
+            MultiSigRequestAction::SetDecommissionBeneficiary { beneficiary_id } => {
+                match beneficiary_id {
+                    Some(beneficiary_id) => {
+                        format!("Set decommission beneficiary to {}", beneficiary_id)
+                    }
+                    None => "Remove decommission beneficiary".to_string(),
+                }
+            }
+            MultiSigRequestAction::SetRequireMemoAbove { require_memo_above } => {
+                match require_memo_above {
+                    Some(amount) => {
+                        format!("Require memo for transfers above {} yⓃ", amount.0)
+                    }
+                    None => "Remove memo requirement for transfers".to_string(),
+                }
+            }
+            MultiSigRequestAction::SetMaxContractCodeSize {
+                max_contract_code_size,
+            } => format!(
+                "Set maximum contract code size to {} bytes",
+                max_contract_code_size.0
+            ),
+            MultiSigRequestAction::SetAutoPrune {
+                auto_prune,
+                prune_after,
+            } => format!(
+                "Set auto prune to {} for requests older than {} ns",
+                auto_prune, prune_after.0
+            ),
+        }
+    }
+
+    /// Returns the serde `type` tag of the action, e.g. `"AddKey"`.
+    /// Matched rather than serialized, so large actions like `DeployContract` stay cheap.
+    fn kind(&self) -> &'static str {
+        match self {
+            MultiSigRequestAction::Transfer { .. } => "Transfer",
+            MultiSigRequestAction::CreateAccount => "CreateAccount",
+            MultiSigRequestAction::DeployContract { .. } => "DeployContract",
+            MultiSigRequestAction::DeployContractHash { .. } => "DeployContractHash",
+            MultiSigRequestAction::AddMember { .. } => "AddMember",
+            MultiSigRequestAction::DeleteMember { .. } => "DeleteMember",
+            MultiSigRequestAction::DeleteMemberByKey { .. } => "DeleteMemberByKey",
+            MultiSigRequestAction::AddKey { .. } => "AddKey",
+            MultiSigRequestAction::FunctionCall { .. } => "FunctionCall",
+            MultiSigRequestAction::NftTransfer { .. } => "NftTransfer",
+            MultiSigRequestAction::FtTransfer { .. } => "FtTransfer",
+            MultiSigRequestAction::SweepTransfer { .. } => "SweepTransfer",
+            MultiSigRequestAction::ProxyRequest { .. } => "ProxyRequest",
+            MultiSigRequestAction::MultiTransfer { .. } => "MultiTransfer",
+            MultiSigRequestAction::SetNumConfirmations { .. } => "SetNumConfirmations",
+            MultiSigRequestAction::SetConfirmationPercentage { .. } => "SetConfirmationPercentage",
+            MultiSigRequestAction::SetPaused { .. } => "SetPaused",
+            MultiSigRequestAction::SetFailurePauseThreshold { .. } => "SetFailurePauseThreshold",
+            MultiSigRequestAction::SetRequireIndependentConfirmation { .. } => {
+                "SetRequireIndependentConfirmation"
+            }
+            MultiSigRequestAction::SetActiveRequestsLimit { .. } => "SetActiveRequestsLimit",
+            MultiSigRequestAction::SetMaxTotalRequests { .. } => "SetMaxTotalRequests",
+            MultiSigRequestAction::CancelMemberRequests { .. } => "CancelMemberRequests",
+            MultiSigRequestAction::SetRejectDuplicates { .. } => "SetRejectDuplicates",
+            MultiSigRequestAction::SetRequestNonceFloor { .. } => "SetRequestNonceFloor",
+            MultiSigRequestAction::SetDefaultCallGas { .. } => "SetDefaultCallGas",
+            MultiSigRequestAction::SetSelfCallDenylist { .. } => "SetSelfCallDenylist",
+            MultiSigRequestAction::RepairInvariants { .. } => "RepairInvariants",
+            MultiSigRequestAction::RefreshKeyAllowances => "RefreshKeyAllowances",
+            MultiSigRequestAction::SetMinRequestInterval { .. } => "SetMinRequestInterval",
+            MultiSigRequestAction::SetActionKindCooldown { .. } => "SetActionKindCooldown",
+            MultiSigRequestAction::CreateCommittee { .. } => "CreateCommittee",
+            MultiSigRequestAction::DissolveCommittee { .. } => "DissolveCommittee",
+            MultiSigRequestAction::SetConfirmationTtl { .. } => "SetConfirmationTtl",
+            MultiSigRequestAction::AddObserver { .. } => "AddObserver",
+            MultiSigRequestAction::RemoveObserver { .. } => "RemoveObserver",
+            MultiSigRequestAction::SetNewMemberCooldown { .. } => "SetNewMemberCooldown",
+            MultiSigRequestAction::ReplaceAllMembers { .. } => "ReplaceAllMembers",
+            MultiSigRequestAction::LockMembership { .. } => "LockMembership",
+            MultiSigRequestAction::SetRetryFailedExecutions { .. } => "SetRetryFailedExecutions",
+            MultiSigRequestAction::SetMaxFunctionCallDeposit { .. } => "SetMaxFunctionCallDeposit",
+            MultiSigRequestAction::SetMaxFunctionCallGas { .. } => "SetMaxFunctionCallGas",
+            MultiSigRequestAction::SetLabel { .. } => "SetLabel",
+            MultiSigRequestAction::SetRejectReplays { .. } => "SetRejectReplays",
+            MultiSigRequestAction::SetMemberReceivers { .. } => "SetMemberReceivers",
+            MultiSigRequestAction::SetMemberWeight { .. } => "SetMemberWeight",
+            MultiSigRequestAction::SetQuorumPolicy { .. } => "SetQuorumPolicy",
+            MultiSigRequestAction::ApproveAccountMigration { .. } => "ApproveAccountMigration",
+            MultiSigRequestAction::SetStrictActiveRequestsLimit { .. } => {
+                "SetStrictActiveRequestsLimit"
+            }
+            MultiSigRequestAction::SetCollectEarlyConfirmations { .. } => {
+                "SetCollectEarlyConfirmations"
+            }
+            MultiSigRequestAction::DecommissionContract { .. } => "DecommissionContract",
+            MultiSigRequestAction::SetDecommissionBeneficiary { .. } => {
+                "SetDecommissionBeneficiary"
+            }
+            MultiSigRequestAction::SetRequireMemoAbove { .. } => "SetRequireMemoAbove",
+            MultiSigRequestAction::SetMaxContractCodeSize { .. } => "SetMaxContractCodeSize",
+            MultiSigRequestAction::SetAutoPrune { .. } => "SetAutoPrune",
+        }
+    }
+
+    /// Returns account ids referenced by the action.
+    fn account_ids(&self) -> Vec<&AccountId> {
+        fn member_account_id(member: &MultisigMember) -> Option<&AccountId> {
+            match member {
+                MultisigMember::Account { account_id } => Some(account_id),
+                MultisigMember::AccessKey { .. } => None,
+            }
+        }
+        match self {
+            MultiSigRequestAction::AddMember { member }
+            | MultiSigRequestAction::DeleteMember { member }
+            | MultiSigRequestAction::CancelMemberRequests { member }
+            | MultiSigRequestAction::AddObserver { member }
+            | MultiSigRequestAction::RemoveObserver { member } => {
+                member_account_id(member).into_iter().collect()
+            }
+            MultiSigRequestAction::CreateCommittee { members, .. }
+            | MultiSigRequestAction::ReplaceAllMembers { members, .. } => {
+                members.iter().filter_map(member_account_id).collect()
+            }
+            MultiSigRequestAction::AddKey {
+                permission: Some(permission),
+                ..
+            } => vec![&permission.receiver_id],
+            MultiSigRequestAction::NftTransfer {
+                contract_id,
+                receiver_id,
+                ..
+            } => vec![contract_id, receiver_id],
+            MultiSigRequestAction::FtTransfer {
+                token_id,
+                receiver_id,
+                ..
+            } => vec![token_id, receiver_id],
+            MultiSigRequestAction::SweepTransfer { receiver_id, .. } => vec![receiver_id],
+            MultiSigRequestAction::ProxyRequest {
+                multisig_account, ..
+            } => vec![multisig_account],
+            MultiSigRequestAction::MultiTransfer { transfers } => {
+                transfers.iter().map(|(account_id, _)| account_id).collect()
+            }
+            MultiSigRequestAction::DecommissionContract { beneficiary_id }
+            | MultiSigRequestAction::SetDecommissionBeneficiary { beneficiary_id } => {
+                beneficiary_id.iter().collect()
+            }
+            MultiSigRequestAction::ApproveAccountMigration {
+                account_id,
+                new_account_id,
+            } => vec![account_id, new_account_id],
+            MultiSigRequestAction::SetMemberReceivers { receivers, .. } => {
+                receivers.iter().collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Returns true if the action can only be sent to the multisig itself.
+    fn is_self_only(&self) -> bool {
+        !matches!(
+            self,
+            MultiSigRequestAction::Transfer { .. }
+                | MultiSigRequestAction::CreateAccount
+                | MultiSigRequestAction::DeployContract { .. }
+                | MultiSigRequestAction::DeployContractHash { .. }
+                | MultiSigRequestAction::FunctionCall { .. }
+                | MultiSigRequestAction::NftTransfer { .. }
+                | MultiSigRequestAction::FtTransfer { .. }
+                | MultiSigRequestAction::SweepTransfer { .. }
+                | MultiSigRequestAction::ProxyRequest { .. }
+        )
+    }
+
+    /// Returns true if the action must be the only action of the request.
+    fn is_single_action(&self) -> bool {
+        matches!(
+            self,
+            MultiSigRequestAction::SetNumConfirmations { .. }
+                | MultiSigRequestAction::SetActiveRequestsLimit { .. }
+                | MultiSigRequestAction::SetRejectDuplicates { .. }
+                | MultiSigRequestAction::SetRequestNonceFloor { .. }
+                | MultiSigRequestAction::SetDefaultCallGas { .. }
+                | MultiSigRequestAction::SetSelfCallDenylist { .. }
+                | MultiSigRequestAction::RepairInvariants { .. }
+                | MultiSigRequestAction::SetMinRequestInterval { .. }
+                | MultiSigRequestAction::SetActionKindCooldown { .. }
+                | MultiSigRequestAction::SetConfirmationTtl { .. }
+                | MultiSigRequestAction::SetNewMemberCooldown { .. }
+                | MultiSigRequestAction::ReplaceAllMembers { .. }
+                | MultiSigRequestAction::LockMembership { .. }
+                | MultiSigRequestAction::SetRetryFailedExecutions { .. }
+                | MultiSigRequestAction::SetMaxFunctionCallDeposit { .. }
+                | MultiSigRequestAction::SetMaxFunctionCallGas { .. }
+                | MultiSigRequestAction::SetLabel { .. }
+                | MultiSigRequestAction::SetRejectReplays { .. }
+                | MultiSigRequestAction::SetStrictActiveRequestsLimit { .. }
+                | MultiSigRequestAction::SetCollectEarlyConfirmations { .. }
+                | MultiSigRequestAction::DecommissionContract { .. }
+                | MultiSigRequestAction::SetDecommissionBeneficiary { .. }
+                | MultiSigRequestAction::SetRequireMemoAbove { .. }
+                | MultiSigRequestAction::SetMaxContractCodeSize { .. }
+                | MultiSigRequestAction::SetMemberReceivers { .. }
+                | MultiSigRequestAction::SetMemberWeight { .. }
+                | MultiSigRequestAction::SetQuorumPolicy { .. }
+                | MultiSigRequestAction::SetAutoPrune { .. }
+                | MultiSigRequestAction::MultiTransfer { .. }
+                | MultiSigRequestAction::SetConfirmationPercentage { .. }
+                | MultiSigRequestAction::SetPaused { .. }
+                | MultiSigRequestAction::SetFailurePauseThreshold { .. }
+                | MultiSigRequestAction::SetRequireIndependentConfirmation { .. }
+                | MultiSigRequestAction::SetMaxTotalRequests { .. }
+        )
+    }
+
+    /// Returns true if the action changes who controls the multisig.
+    fn is_self_modifying(&self) -> bool {
+        matches!(
+            self,
+            MultiSigRequestAction::SetNumConfirmations { .. }
+                | MultiSigRequestAction::AddMember { .. }
+                | MultiSigRequestAction::DeleteMember { .. }
+                | MultiSigRequestAction::DeleteMemberByKey { .. }
+                | MultiSigRequestAction::AddKey { .. }
+        )
+    }
+}
+
+fn describe_member(member: &MultisigMember) -> String {
+    match member {
+        MultisigMember::AccessKey { public_key } => {
+            format!("AccessKey:{}", String::from(public_key))
+        }
+        MultisigMember::Account { account_id } => format!("Account:{}", account_id),
+    }
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
 /// The request the user makes specifying the receiving account and actions they want to execute (1 tx)
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
-#[cfg_attr(test, derive(PartialEq, Clone))]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
 #[serde(crate = "near_sdk::serde")]
 pub struct MultiSigRequest {
     receiver_id: AccountId,
     actions: Vec<MultiSigRequestAction>,
+    /// Optional category like "payroll" to filter requests by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    /// Time in nanoseconds before which the request can't be executed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    not_before: Option<U64>,
+    /// Number of confirmations this request needs if it's more than the multisig requires.
+    /// Never lowers the threshold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_confirmations_override: Option<u32>,
+}
+
+/// Request queued on another multisig by `ProxyRequest`.
+/// Borsh is implemented by hand, derived bounds can't be resolved for the recursive type.
+#[derive(Serialize, Deserialize, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+#[serde(transparent, crate = "near_sdk::serde")]
+pub struct ProxiedRequest(MultiSigRequest);
+
+impl BorshSerialize for ProxiedRequest {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        BorshSerialize::serialize(&self.0, writer)
+    }
+}
+
+impl BorshDeserialize for ProxiedRequest {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        <MultiSigRequest as BorshDeserialize>::deserialize(buf).map(ProxiedRequest)
+    }
 }
 
 /// An internal request wrapped with the signer_pk and added timestamp to determine num_requests_pk and prevent against malicious key holder gas attacks
@@ -89,6 +889,13 @@ pub struct MultiSigRequestWithSigner {
     request: MultiSigRequest,
     member: MultisigMember,
     added_timestamp: u64,
+    /// Time in nanoseconds from which the request can't be confirmed, set by `add_request_with_ttl`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    /// Hash of the voting members when the request was added, to detect membership changes
+    /// before execution.
+    #[serde(default)]
+    voting_members_hash: Vec<u8>,
 }
 
 /// Represents member of the multsig: either account or access key to given account.
@@ -101,16 +908,247 @@ pub enum MultisigMember {
 
 impl ToString for MultisigMember {
     fn to_string(&self) -> String {
-        serde_json::to_string(&self).unwrap_or_else(|_| env::panic_str("Failed to serialize"))
+        serde_json::to_string(&self)
+            .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize"))
     }
 }
 
+/// Contract version together with the basic multisig configuration.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractInfo {
+    pub version: String,
+    pub num_confirmations: u32,
+    pub num_members: u64,
+}
+
+/// Active request with its confirmations, as exported by `export_state`.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct RequestSnapshot {
+    pub request_id: RequestId,
+    pub member: MultisigMember,
+    pub added_timestamp: U64,
+    pub request: MultiSigRequest,
+    /// Confirming members with the time they confirmed.
+    pub confirmations: Vec<(String, U64)>,
+}
+
+/// Contract state for off-chain backups, with one page of the active requests.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct StateSnapshot {
+    pub members: Vec<MultisigMember>,
+    pub num_confirmations: u32,
+    pub request_nonce: RequestId,
+    pub active_requests_limit: u32,
+    /// Total number of active requests, to know when all pages were exported.
+    pub num_requests: u64,
+    pub requests: Vec<RequestSnapshot>,
+}
+
+/// Built-in defaults that can't be configured, so clients don't have to hardcode them.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct Defaults {
+    /// Allowance of access keys added for members, 0 means unlimited.
+    pub default_allowance: U128,
+    /// Time in nanoseconds after which any member can delete a request.
+    pub request_cooldown: U64,
+}
+
+/// Metrics for monitoring the multisig, returned by `health`.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct HealthReport {
+    pub num_members: u64,
+    /// Number of confirmations requests need.
+    pub num_confirmations: u32,
+    pub num_requests: u64,
+    /// Number of requests past their cooldown, which any member can delete.
+    pub num_deletable_requests: u64,
+    /// Whether members and committees can reach the number of confirmations and the weight threshold.
+    pub threshold_reachable: bool,
+}
+
+/// Member together with its voting details and bookkeeping.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct MemberInfo {
+    pub member: MultisigMember,
+    /// Weight of the member's confirmations under the `CountAndWeight` quorum policy.
+    pub weight: u32,
+    pub observer: bool,
+    pub added_timestamp: U64,
+    pub num_requests: u32,
+}
+
+/// Member listed by `get_members`, observers are marked with `"observer": true`.
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(crate = "near_sdk::serde")]
+pub struct MemberWithRole {
+    #[serde(flatten)]
+    pub member: MultisigMember,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub observer: bool,
+}
+
+/// What confirmations a request needs to execute.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+#[serde(crate = "near_sdk::serde")]
+pub enum QuorumPolicy {
+    /// Enough confirmations.
+    Count,
+    /// Enough confirmations whose members also have at least `weight_threshold` combined weight.
+    CountAndWeight,
+}
+
+/// Group of members that confirm requests as a single member once `threshold` of them confirmed.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq, Clone))]
+#[serde(crate = "near_sdk::serde")]
+pub struct Committee {
+    pub members: Vec<MultisigMember>,
+    pub threshold: u32,
+}
+
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
     Members,
     Requests,
     Confirmations,
     NumRequestsPk,
+    ClientNonces,
+    LastRequestTimestamp,
+    Committees,
+    CommitteeConfirmations,
+    Observers,
+    MemberAddedTimestamp,
+    ExecutingRequests,
+    SignedConfirmations,
+    AccountMigrations,
+    MemberAllowances,
+    ActionKindCooldowns,
+    ActionKindLastAdded,
+    MemberReceivers,
+    MemberWeights,
+    RequestNotes,
+    MemberActivity,
+}
+
+/// Action as stored by versions before `migrate`, in the original variant order.
+#[derive(BorshDeserialize, BorshSerialize)]
+enum OldMultiSigRequestAction {
+    Transfer {
+        amount: U128,
+    },
+    CreateAccount,
+    DeployContract {
+        code: Base64VecU8,
+    },
+    AddMember {
+        member: MultisigMember,
+    },
+    DeleteMember {
+        member: MultisigMember,
+    },
+    AddKey {
+        public_key: PublicKey,
+        permission: Option<FunctionCallPermission>,
+    },
+    FunctionCall {
+        method_name: String,
+        args: Base64VecU8,
+        deposit: U128,
+        gas: U64,
+    },
+    SetNumConfirmations {
+        num_confirmations: u32,
+    },
+    SetActiveRequestsLimit {
+        active_requests_limit: u32,
+    },
+}
+
+impl From<OldMultiSigRequestAction> for MultiSigRequestAction {
+    fn from(action: OldMultiSigRequestAction) -> Self {
+        match action {
+            OldMultiSigRequestAction::Transfer { amount } => {
+                MultiSigRequestAction::Transfer { amount, memo: None }
+            }
+            OldMultiSigRequestAction::CreateAccount => MultiSigRequestAction::CreateAccount,
+            OldMultiSigRequestAction::DeployContract { code } => {
+                MultiSigRequestAction::DeployContract { code }
+            }
+            OldMultiSigRequestAction::AddMember { member } => {
+                MultiSigRequestAction::AddMember { member }
+            }
+            OldMultiSigRequestAction::DeleteMember { member } => {
+                MultiSigRequestAction::DeleteMember { member }
+            }
+            OldMultiSigRequestAction::AddKey {
+                public_key,
+                permission,
+            } => MultiSigRequestAction::AddKey {
+                public_key,
+                permission,
+            },
+            OldMultiSigRequestAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                gas,
+            } => MultiSigRequestAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                gas,
+            },
+            OldMultiSigRequestAction::SetNumConfirmations { num_confirmations } => {
+                MultiSigRequestAction::SetNumConfirmations { num_confirmations }
+            }
+            OldMultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit,
+            } => MultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit,
+            },
+        }
+    }
+}
+
+/// Request as stored by versions before `migrate`.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldMultiSigRequest {
+    receiver_id: AccountId,
+    actions: Vec<OldMultiSigRequestAction>,
+}
+
+/// Request with its signer as stored by versions before `migrate`.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldMultiSigRequestWithSigner {
+    request: OldMultiSigRequest,
+    member: MultisigMember,
+    added_timestamp: u64,
+}
+
+/// Contract state as stored by versions before `migrate`.
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldMultiSigContract {
+    members: UnorderedSet<MultisigMember>,
+    num_confirmations: u32,
+    request_nonce: u32,
+    requests: UnorderedMap<u32, OldMultiSigRequestWithSigner>,
+    confirmations: LookupMap<u32, HashSet<String>>,
+    num_requests_pk: LookupMap<String, u32>,
+    active_requests_limit: u32,
 }
 
 #[near_bindgen]
@@ -118,27 +1156,253 @@ pub enum StorageKeys {
 pub struct MultiSigContract {
     /// Members of the multisig.
     members: UnorderedSet<MultisigMember>,
+    /// Members that can only view, they can't add or confirm requests.
+    observers: UnorderedSet<MultisigMember>,
     /// Number of confirmations required.
     num_confirmations: u32,
+    /// Percentage of voting members required to confirm, overrides `num_confirmations` if set.
+    confirmation_percentage: Option<u8>,
     /// Latest request nonce.
     request_nonce: RequestId,
+    /// Lowest id new requests can get. Only ever increases, so ids are never reused.
+    request_nonce_floor: RequestId,
     /// All active requests.
     requests: UnorderedMap<RequestId, MultiSigRequestWithSigner>,
-    /// All confirmations for active requests.
-    confirmations: LookupMap<RequestId, HashSet<String>>,
+    /// All confirmations for active requests with the time each was given.
+    confirmations: LookupMap<RequestId, HashMap<String, u64>>,
+    /// Time in nanoseconds after which a confirmation stops counting. 0 means never.
+    confirmation_ttl: u64,
+    /// Allow confirming requests before their `not_before` time.
+    collect_early_confirmations: bool,
     /// Number of requests per member.
     num_requests_pk: LookupMap<String, u32>,
     /// Limit number of active requests per member.
     active_requests_limit: u32,
+    /// Limit number of active requests of all members together.
+    max_total_requests: Option<u32>,
+    /// Fail lowering the limit below active requests of some member instead of logging an event.
+    strict_active_requests_limit: bool,
+    /// Reject requests identical to an already pending one.
+    reject_duplicates: bool,
+    /// Reject requests identical to one executed within `replay_window`.
+    reject_replays: bool,
+    /// Time in nanoseconds during which executed requests can't be added again.
+    replay_window: u64,
+    /// Prune expired requests when a request is added.
+    auto_prune: bool,
+    /// Time in nanoseconds after being added when a request expires and can be pruned.
+    prune_after: u64,
+    /// Hashes of recently executed requests with the time they were executed, oldest first.
+    executed_request_hashes: Vec<(Vec<u8>, u64)>,
+    /// Gas attached to calls made by helper actions.
+    default_call_gas: Gas,
+    /// Methods of this contract that can't be called via `FunctionCall` on itself.
+    self_call_denylist: Vec<String>,
+    /// Minimum time in nanoseconds between requests added by the same member.
+    min_request_interval: u64,
+    /// Time of the latest request added by each member, tracked while the interval is set.
+    last_request_timestamp: UnorderedMap<String, u64>,
+    /// Minimum time in nanoseconds between requests containing an action of given kind.
+    action_kind_cooldowns: UnorderedMap<String, u64>,
+    /// Time of the latest request containing each action kind that has a cooldown.
+    action_kind_last_added: LookupMap<String, u64>,
+    /// Time in nanoseconds after being added during which a member can't confirm requests.
+    new_member_cooldown: u64,
+    /// Time each member was added at.
+    member_added_timestamp: UnorderedMap<String, u64>,
+    /// Committees by name.
+    committees: UnorderedMap<String, Committee>,
+    /// Confirmations of committee members per request, by committee name.
+    committee_confirmations: LookupMap<RequestId, HashMap<String, HashSet<String>>>,
+    /// Recent client nonces per member with the requests they created.
+    client_nonces: LookupMap<String, Vec<(String, RequestId)>>,
+    /// Allowance the access key of each access key member was added with.
+    member_allowances: UnorderedMap<String, Balance>,
+    /// Approved new account ids of account members, by their current account id.
+    account_migrations: LookupMap<AccountId, AccountId>,
+    /// Receivers each restricted member can add requests to.
+    member_receivers: UnorderedMap<String, HashSet<AccountId>>,
+    /// Weights of members that don't have the default weight of 1.
+    member_weights: UnorderedMap<String, u32>,
+    /// Notes confirmers attached to each request, with the member that attached them.
+    request_notes: UnorderedMap<RequestId, Vec<(String, String)>>,
+    /// Number of actions of each kind each member confirmed, by member and action kind.
+    member_activity: UnorderedMap<(String, String), u32>,
+    /// Whether requests also need `weight_threshold` of combined member weight.
+    quorum_policy: QuorumPolicy,
+    /// Combined member weight requests need under the `CountAndWeight` policy.
+    weight_threshold: u32,
+    /// Request nonce and ids of the requests each member confirmed by signature at that nonce.
+    signed_confirmations: LookupMap<String, (RequestId, Vec<RequestId>)>,
+    /// Human readable label of the multisig.
+    label: Option<String>,
+    /// Largest deposit a `FunctionCall` action can attach.
+    max_function_call_deposit: Option<Balance>,
+    /// Transfers above this amount need a memo.
+    require_memo_above: Option<Balance>,
+    /// Receiver of the balance of `DecommissionContract` actions that don't name one.
+    decommission_beneficiary: Option<AccountId>,
+    /// Largest code size in bytes of contracts requests can deploy.
+    max_contract_code_size: u64,
+    /// Largest gas a `FunctionCall` action can attach.
+    max_function_call_gas: Option<Gas>,
+    /// Members can't be the first to confirm their own self-modifying requests.
+    require_independent_confirmation: bool,
+    /// Only requests to the multisig itself can be added and executed while paused.
+    paused: bool,
+    /// Time in nanoseconds until which members can't be added or removed.
+    membership_locked_until: u64,
+    /// Number of consecutive failed executions that pauses the multisig, 0 means never.
+    failure_pause_threshold: u32,
+    /// Number of executions that failed since the last successful one.
+    consecutive_failures: u32,
+    /// Re-queue requests whose execution failed instead of dropping them.
+    retry_failed_executions: bool,
+    /// Requests being executed, kept while `retry_failed_executions` is set.
+    executing_requests: LookupMap<RequestId, MultiSigRequestWithSigner>,
+}
+
+#[ext_contract(ext_self)]
+pub trait SelfCallbacks {
+    fn on_execute(&mut self, request_id: RequestId, receiver_id: AccountId) -> bool;
+}
+
+/// Logs an event in the NEP-297 format.
+fn log_event(event: &str, data: serde_json::Value) {
+    let event = json!({
+        "standard": "multisig",
+        "version": "1.0.0",
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
+
+/// Errors with stable codes, reported as `"<code>: <message>"` so clients can match on the code.
+#[derive(Clone, Copy)]
+pub enum MultiSigError {
+    NotMember,
+    UnexpectedMember,
+    NoRequest,
+    Cooldown,
+    AlreadyConfirmed,
+    TooManyRequests,
+    RateLimited,
+    DuplicateRequest,
+    InvalidArgument,
+    InvalidSignature,
+    InvalidConfig,
+    ReceiverMismatch,
+    SeparateRequest,
+    MethodDenied,
+    CodeMismatch,
+    NoCommittee,
+    Observer,
+    MemberCooldown,
+    InsufficientBalance,
+    NotYetActive,
+    Paused,
+    NotIndependent,
+    MembershipLocked,
+    Expired,
+    MembersChanged,
+    Internal,
+}
+
+impl MultiSigError {
+    pub fn code(self) -> &'static str {
+        match self {
+            MultiSigError::NotMember => "E_NOT_MEMBER",
+            MultiSigError::UnexpectedMember => "E_UNEXPECTED_MEMBER",
+            MultiSigError::NoRequest => "E_NO_REQUEST",
+            MultiSigError::Cooldown => "E_COOLDOWN",
+            MultiSigError::AlreadyConfirmed => "E_ALREADY_CONFIRMED",
+            MultiSigError::TooManyRequests => "E_TOO_MANY_REQUESTS",
+            MultiSigError::RateLimited => "E_RATE_LIMITED",
+            MultiSigError::DuplicateRequest => "E_DUPLICATE_REQUEST",
+            MultiSigError::InvalidArgument => "E_INVALID_ARGUMENT",
+            MultiSigError::InvalidSignature => "E_INVALID_SIGNATURE",
+            MultiSigError::InvalidConfig => "E_INVALID_CONFIG",
+            MultiSigError::ReceiverMismatch => "E_RECEIVER_MISMATCH",
+            MultiSigError::SeparateRequest => "E_SEPARATE_REQUEST",
+            MultiSigError::MethodDenied => "E_METHOD_DENIED",
+            MultiSigError::CodeMismatch => "E_CODE_MISMATCH",
+            MultiSigError::NoCommittee => "E_NO_COMMITTEE",
+            MultiSigError::Observer => "E_OBSERVER",
+            MultiSigError::MemberCooldown => "E_MEMBER_COOLDOWN",
+            MultiSigError::InsufficientBalance => "E_INSUFFICIENT_BALANCE",
+            MultiSigError::NotYetActive => "E_NOT_YET_ACTIVE",
+            MultiSigError::Paused => "E_PAUSED",
+            MultiSigError::NotIndependent => "E_NOT_INDEPENDENT",
+            MultiSigError::MembershipLocked => "E_MEMBERSHIP_LOCKED",
+            MultiSigError::Expired => "E_EXPIRED",
+            MultiSigError::MembersChanged => "E_MEMBERS_CHANGED",
+            MultiSigError::Internal => "E_INTERNAL",
+        }
+    }
+}
+
+fn panic_error(error: MultiSigError, message: &str) -> ! {
+    env::panic_str(&format!("{}: {}", error.code(), message))
 }
 
 #[inline]
-fn assert(condition: bool, error: &str) {
+fn assert(condition: bool, error: MultiSigError, message: &str) {
     if !condition {
-        env::panic_str(error);
+        panic_error(error, message);
+    }
+}
+
+/// Verifies ed25519 signature of the message by given public key.
+fn verify_signature(public_key: &PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    let key_bytes = public_key.as_bytes();
+    // first byte is the curve type, only ed25519 keys are supported
+    if key_bytes.len() != 33 || key_bytes[0] != 0 {
+        return false;
+    }
+    let public_key = match ed25519_dalek::PublicKey::from_bytes(&key_bytes[1..]) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    let signature = match ed25519_dalek::Signature::try_from(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    public_key.verify(message, &signature).is_ok()
+}
+
+/// Returns `percentage` of `num_members`, rounded up.
+fn percentage_of_members(num_members: u64, percentage: u8) -> u32 {
+    let scaled = num_members * percentage as u64;
+    let required = scaled / 100;
+    if required * 100 < scaled {
+        required as u32 + 1
+    } else {
+        required as u32
     }
 }
 
+/// Returns true if `num_confirmations` and `extra` more reach `threshold`.
+/// Compares as `u64` so neither the cast of the length nor the addition can wrap.
+fn reaches_threshold(num_confirmations: usize, extra: u64, threshold: u32) -> bool {
+    (num_confirmations as u64).saturating_add(extra) >= threshold as u64
+}
+
+/// Hash of the Borsh-serialized receiver and actions of the request.
+/// Memos, tag and scheduling don't make otherwise identical requests different.
+fn request_hash(request: &MultiSigRequest) -> Vec<u8> {
+    let actions: Vec<MultiSigRequestAction> = request
+        .actions
+        .iter()
+        .map(MultiSigRequestAction::without_memo)
+        .collect();
+    env::sha256(
+        &(&request.receiver_id, actions)
+            .try_to_vec()
+            .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize")),
+    )
+}
+
 #[near_bindgen]
 impl MultiSigContract {
     /// Initialize multisig contract.
@@ -148,17 +1412,10 @@ impl MultiSigContract {
     pub fn new(members: Vec<MultisigMember>, num_confirmations: u32) -> Self {
         assert(
             members.len() >= num_confirmations as usize,
+            MultiSigError::InvalidConfig,
             "Members list must be equal or larger than number of confirmations",
         );
-        let mut multisig = Self {
-            members: UnorderedSet::new(StorageKeys::Members),
-            num_confirmations,
-            request_nonce: 0,
-            requests: UnorderedMap::new(StorageKeys::Requests),
-            confirmations: LookupMap::new(StorageKeys::Confirmations),
-            num_requests_pk: LookupMap::new(StorageKeys::NumRequestsPk),
-            active_requests_limit: ACTIVE_REQUESTS_LIMIT,
-        };
+        let mut multisig = Self::with_defaults(num_confirmations);
         let mut promise = Promise::new(env::current_account_id());
         for member in members {
             promise = multisig.add_member(promise, member);
@@ -166,23 +1423,238 @@ impl MultiSigContract {
         multisig
     }
 
-    /// Add request for multisig.
-    pub fn add_request(&mut self, request: MultiSigRequest) -> RequestId {
-        let current_member = self.current_member().unwrap_or_else(|| {
-            env::panic_str(
-                "Predecessor must be a member or transaction signed with key of given account",
+    /// Migrate the state written by versions with `u32` request ids and untimed confirmations.
+    /// Has to be called by the multisig itself right after deploying the new code.
+    /// Active requests and their confirmations are kept, migrated confirmations count as given now.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate() -> Self {
+        let mut old: OldMultiSigContract = env::state_read()
+            .unwrap_or_else(|| panic_error(MultiSigError::Internal, "Failed to read old state"));
+        let mut multisig = Self::with_defaults(old.num_confirmations);
+        multisig.request_nonce = old.request_nonce as RequestId;
+        multisig.active_requests_limit = old.active_requests_limit;
+        // members and request counts keep their storage keys and layout
+        multisig.members = old.members;
+        multisig.num_requests_pk = old.num_requests_pk;
+        let voting_members_hash = multisig.voting_members_hash();
+        // ids are part of the storage keys, so requests are removed and added back with the new id type
+        let old_requests = old.requests.to_vec();
+        old.requests.clear();
+        for (request_id, old_request) in old_requests {
+            let request_id = request_id as RequestId;
+            let request = MultiSigRequestWithSigner {
+                request: MultiSigRequest {
+                    receiver_id: old_request.request.receiver_id,
+                    actions: old_request
+                        .request
+                        .actions
+                        .into_iter()
+                        .map(Into::into)
+                        .collect(),
+                    tag: None,
+                    not_before: None,
+                    min_confirmations_override: None,
+                },
+                member: old_request.member,
+                added_timestamp: old_request.added_timestamp,
+                expires_at: None,
+                voting_members_hash: voting_members_hash.clone(),
+            };
+            multisig.requests.insert(&request_id, &request);
+            if let Some(confirmed) = old.confirmations.remove(&(request_id as u32)) {
+                let confirmations: HashMap<String, u64> = confirmed
+                    .into_iter()
+                    .map(|member_id| (member_id, env::block_timestamp()))
+                    .collect();
+                multisig.confirmations.insert(&request_id, &confirmations);
+            }
+        }
+        multisig
+    }
+
+    /// Contract with the given number of confirmations and everything else at its default.
+    fn with_defaults(num_confirmations: u32) -> Self {
+        Self {
+            members: UnorderedSet::new(StorageKeys::Members),
+            observers: UnorderedSet::new(StorageKeys::Observers),
+            num_confirmations,
+            confirmation_percentage: None,
+            request_nonce: 0,
+            request_nonce_floor: 0,
+            requests: UnorderedMap::new(StorageKeys::Requests),
+            confirmations: LookupMap::new(StorageKeys::Confirmations),
+            confirmation_ttl: 0,
+            collect_early_confirmations: true,
+            num_requests_pk: LookupMap::new(StorageKeys::NumRequestsPk),
+            active_requests_limit: ACTIVE_REQUESTS_LIMIT,
+            max_total_requests: None,
+            strict_active_requests_limit: false,
+            reject_duplicates: false,
+            reject_replays: false,
+            replay_window: 0,
+            auto_prune: false,
+            prune_after: 0,
+            executed_request_hashes: vec![],
+            default_call_gas: DEFAULT_CALL_GAS,
+            self_call_denylist: vec![],
+            min_request_interval: 0,
+            last_request_timestamp: UnorderedMap::new(StorageKeys::LastRequestTimestamp),
+            action_kind_cooldowns: UnorderedMap::new(StorageKeys::ActionKindCooldowns),
+            action_kind_last_added: LookupMap::new(StorageKeys::ActionKindLastAdded),
+            new_member_cooldown: 0,
+            member_added_timestamp: UnorderedMap::new(StorageKeys::MemberAddedTimestamp),
+            committees: UnorderedMap::new(StorageKeys::Committees),
+            committee_confirmations: LookupMap::new(StorageKeys::CommitteeConfirmations),
+            client_nonces: LookupMap::new(StorageKeys::ClientNonces),
+            signed_confirmations: LookupMap::new(StorageKeys::SignedConfirmations),
+            account_migrations: LookupMap::new(StorageKeys::AccountMigrations),
+            member_receivers: UnorderedMap::new(StorageKeys::MemberReceivers),
+            member_weights: UnorderedMap::new(StorageKeys::MemberWeights),
+            request_notes: UnorderedMap::new(StorageKeys::RequestNotes),
+            member_activity: UnorderedMap::new(StorageKeys::MemberActivity),
+            quorum_policy: QuorumPolicy::Count,
+            weight_threshold: 0,
+            member_allowances: UnorderedMap::new(StorageKeys::MemberAllowances),
+            label: None,
+            max_function_call_deposit: None,
+            require_memo_above: None,
+            decommission_beneficiary: None,
+            max_contract_code_size: DEFAULT_MAX_CONTRACT_CODE_SIZE,
+            max_function_call_gas: None,
+            require_independent_confirmation: false,
+            paused: false,
+            membership_locked_until: 0,
+            failure_pause_threshold: 0,
+            consecutive_failures: 0,
+            retry_failed_executions: false,
+            executing_requests: LookupMap::new(StorageKeys::ExecutingRequests),
+        }
+    }
+
+    /// Add request for multisig.
+    pub fn add_request(&mut self, request: MultiSigRequest) -> RequestId {
+        let current_member = self.current_member().unwrap_or_else(|| {
+            panic_error(
+                MultiSigError::NotMember,
+                "Predecessor must be a member or transaction signed with key of given account",
             )
         });
-        // track how many requests this key has made
-        let num_requests = self
-            .num_requests_pk
+        self.insert_request(current_member, request)
+    }
+
+    /// Add request for multisig, deduplicated by a client supplied nonce.
+    /// Repeating the call with the same nonce returns the originally created request id.
+    pub fn add_request_idempotent(
+        &mut self,
+        request: MultiSigRequest,
+        client_nonce: String,
+    ) -> RequestId {
+        assert(
+            client_nonce.len() <= MAX_CLIENT_NONCE_LEN,
+            MultiSigError::InvalidArgument,
+            "Client nonce is too long",
+        );
+        let current_member = self.current_member().unwrap_or_else(|| {
+            panic_error(
+                MultiSigError::NotMember,
+                "Predecessor must be a member or transaction signed with key of given account",
+            )
+        });
+        let mut client_nonces = self
+            .client_nonces
             .get(&current_member.to_string())
-            .unwrap_or(0)
-            + 1;
+            .unwrap_or_default();
+        if let Some((_, request_id)) = client_nonces.iter().find(|(n, _)| n == &client_nonce) {
+            return *request_id;
+        }
+        let request_id = self.insert_request(current_member.clone(), request);
+        // only the most recent nonces are remembered
+        if client_nonces.len() >= MAX_CLIENT_NONCES_PER_MEMBER {
+            client_nonces.remove(0);
+        }
+        client_nonces.push((client_nonce, request_id));
+        self.client_nonces
+            .insert(&current_member.to_string(), &client_nonces);
+        request_id
+    }
+
+    /// Add request for multisig that can't be confirmed or executed after `ttl_ns` nanoseconds.
+    /// Expired requests are pruned like other expired requests when `auto_prune` is set.
+    pub fn add_request_with_ttl(&mut self, request: MultiSigRequest, ttl_ns: U64) -> RequestId {
+        assert(
+            ttl_ns.0 > 0 && ttl_ns.0 <= MAX_REQUEST_TTL,
+            MultiSigError::InvalidArgument,
+            "Request TTL must be positive and at most a year",
+        );
+        let request_id = self.add_request(request);
+        let mut request_with_signer = self
+            .requests
+            .get(&request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::Internal, "Request must exist"));
+        request_with_signer.expires_at = Some(env::block_timestamp() + ttl_ns.0);
+        self.requests.insert(&request_id, &request_with_signer);
+        request_id
+    }
+
+    /// Add request on behalf of an access key member, submitted by a relayer.
+    /// `signature` is an ed25519 signature by the member's key of the Borsh-serialized
+    /// `(current_account_id, request_nonce, request)`, so it can't be replayed once the nonce moves on.
+    pub fn add_request_delegated(
+        &mut self,
+        request: MultiSigRequest,
+        member: MultisigMember,
+        signature: Base64VecU8,
+    ) -> RequestId {
+        assert(
+            self.members.contains(&member),
+            MultiSigError::NotMember,
+            "Delegating member is not a member of this multisig",
+        );
+        let public_key = match &member {
+            MultisigMember::AccessKey { public_key } => public_key,
+            MultisigMember::Account { .. } => panic_error(
+                MultiSigError::InvalidArgument,
+                "Only access key members can delegate requests",
+            ),
+        };
+        let message = self.delegated_request_message(&request);
         assert(
-            num_requests <= self.active_requests_limit,
-            "Account has too many active requests. Confirm or delete some.",
+            verify_signature(public_key, &message, &signature.0),
+            MultiSigError::InvalidSignature,
+            "Invalid signature for delegated request",
         );
+        self.insert_request(member, request)
+    }
+
+    /// Records request originated by given member.
+    fn insert_request(
+        &mut self,
+        current_member: MultisigMember,
+        request: MultiSigRequest,
+    ) -> RequestId {
+        if self.auto_prune {
+            self.prune_expired_requests();
+        }
+        if let Some((error, message)) = self
+            .new_request_problems(&current_member, &request)
+            .into_iter()
+            .next()
+        {
+            panic_error(error, message);
+        }
+        if self.min_request_interval > 0 {
+            self.last_request_timestamp
+                .insert(&current_member.to_string(), &env::block_timestamp());
+        }
+        for kind in self.rate_limited_kinds(&request) {
+            self.action_kind_last_added
+                .insert(&kind, &env::block_timestamp());
+        }
+        // track how many requests this key has made
+        let num_requests = self
+            .get_num_requests_per_member(current_member.clone())
+            .saturating_add(1);
         self.num_requests_pk
             .insert(&current_member.to_string(), &num_requests);
         // add the request
@@ -190,13 +1662,257 @@ impl MultiSigContract {
             member: current_member,
             added_timestamp: env::block_timestamp(),
             request,
+            expires_at: None,
+            voting_members_hash: self.voting_members_hash(),
         };
-        self.requests.insert(&self.request_nonce, &request_added);
-        let confirmations = HashSet::new();
-        self.confirmations
-            .insert(&self.request_nonce, &confirmations);
-        self.request_nonce += 1;
-        self.request_nonce - 1
+        // never hand out ids below the floor, even if the nonce was reset
+        let request_id = self.request_nonce.max(self.request_nonce_floor);
+        self.requests.insert(&request_id, &request_added);
+        // with a single confirmation needed, the first one executes the request right away,
+        // so there's nothing worth storing
+        if self.request_threshold(&request_added.request) > 1 {
+            self.confirmations.insert(&request_id, &HashMap::new());
+        }
+        self.request_nonce = request_id.checked_add(1).unwrap_or_else(|| {
+            panic_error(
+                MultiSigError::TooManyRequests,
+                "Request nonce exhausted, migrate the multisig",
+            )
+        });
+        request_id
+    }
+
+    /// Returns ids of confirmation entries without a request among `limit` ids from `from_index`.
+    fn orphaned_confirmations(&self, from_index: RequestId, limit: u64) -> Vec<RequestId> {
+        (from_index..from_index.saturating_add(limit))
+            .filter(|request_id| {
+                self.requests.get(request_id).is_none()
+                    && self.confirmations.contains_key(request_id)
+            })
+            .collect()
+    }
+
+    /// Returns ids of requests needing more than one confirmation without a confirmation entry
+    /// among `limit` ids from `from_index`. Requests needing one don't get an entry, their first
+    /// confirmation executes them.
+    fn requests_without_confirmations(&self, from_index: RequestId, limit: u64) -> Vec<RequestId> {
+        (from_index..from_index.saturating_add(limit))
+            .filter(|request_id| {
+                matches!(
+                    self.requests.get(request_id),
+                    Some(r) if self.request_threshold(&r.request) > 1
+                ) && !self.confirmations.contains_key(request_id)
+            })
+            .collect()
+    }
+
+    /// Returns true if the request was added with a TTL that has passed.
+    fn is_expired(request_with_signer: &MultiSigRequestWithSigner) -> bool {
+        matches!(request_with_signer.expires_at, Some(expires_at) if env::block_timestamp() >= expires_at)
+    }
+
+    /// Deletes up to `MAX_AUTO_PRUNED_REQUESTS` requests added more than `prune_after` ago,
+    /// or whose TTL passed once the deletion cooldown did.
+    fn prune_expired_requests(&mut self) {
+        let expires_after = self.prune_after.max(REQUEST_COOLDOWN);
+        let expired: Vec<RequestId> = self
+            .requests
+            .iter()
+            .filter(|(_, r)| {
+                env::block_timestamp() > r.added_timestamp + expires_after
+                    || (env::block_timestamp() > r.added_timestamp + REQUEST_COOLDOWN
+                        && Self::is_expired(r))
+            })
+            .map(|(request_id, _)| request_id)
+            .take(MAX_AUTO_PRUNED_REQUESTS)
+            .collect();
+        for request_id in expired {
+            self.remove_request(request_id);
+            log_event("request_pruned", json!({ "request_id": request_id }));
+        }
+    }
+
+    /// Returns the checks that adding the request by given member would fail, without mutating state.
+    fn new_request_problems(
+        &self,
+        member: &MultisigMember,
+        request: &MultiSigRequest,
+    ) -> Vec<(MultiSigError, &'static str)> {
+        let mut problems = vec![];
+        if !env::is_valid_account_id(request.receiver_id.as_bytes()) {
+            problems.push((MultiSigError::InvalidArgument, "Invalid receiver_id"));
+        }
+        if request
+            .actions
+            .iter()
+            .flat_map(|action| action.account_ids())
+            .any(|account_id| !env::is_valid_account_id(account_id.as_bytes()))
+        {
+            problems.push((
+                MultiSigError::InvalidArgument,
+                "Invalid account id in request actions",
+            ));
+        }
+        if request.actions.iter().any(|action| match action {
+            MultiSigRequestAction::DeployContract { code } => {
+                code.0.len() as u64 > self.max_contract_code_size
+            }
+            MultiSigRequestAction::DeployContractHash { code_size, .. } => {
+                code_size.0 > self.max_contract_code_size
+            }
+            _ => false,
+        }) {
+            problems.push((MultiSigError::InvalidArgument, "Contract code too large"));
+        }
+        // an empty list allows every method, including the ones only the contract itself may call
+        if request.actions.iter().any(|action| {
+            matches!(
+                action,
+                MultiSigRequestAction::AddKey {
+                    permission: Some(permission),
+                    ..
+                } if permission.receiver_id == env::current_account_id()
+                    && permission.method_names.is_empty()
+            )
+        }) {
+            problems.push((
+                MultiSigError::InvalidArgument,
+                "Function call keys for this contract must list their method names",
+            ));
+        }
+        // a bare account has no balance and no keys, so nothing can ever use it
+        if request
+            .actions
+            .iter()
+            .any(|action| matches!(action, MultiSigRequestAction::CreateAccount))
+            && !request.actions.iter().any(|action| {
+                matches!(
+                    action,
+                    MultiSigRequestAction::Transfer { .. }
+                        | MultiSigRequestAction::SweepTransfer { .. }
+                        | MultiSigRequestAction::AddKey { .. }
+                )
+            })
+        {
+            problems.push((
+                MultiSigError::InvalidArgument,
+                "CreateAccount must be accompanied by a Transfer or AddKey",
+            ));
+        }
+        for action in &request.actions {
+            if let MultiSigRequestAction::MultiTransfer { transfers } = action {
+                if transfers.is_empty() || transfers.len() > MAX_MULTI_TRANSFER_RECIPIENTS {
+                    problems.push((
+                        MultiSigError::InvalidArgument,
+                        "MultiTransfer must have between 1 and 32 transfers",
+                    ));
+                }
+                if transfers.iter().any(|(_, amount)| amount.0 == 0) {
+                    problems.push((
+                        MultiSigError::InvalidArgument,
+                        "MultiTransfer amounts must be positive",
+                    ));
+                }
+            }
+        }
+        if let Some(receivers) = self.member_receivers.get(&member.to_string()) {
+            if !receivers.contains(&request.receiver_id) {
+                problems.push((
+                    MultiSigError::ReceiverMismatch,
+                    "Receiver is not allowed for this member",
+                ));
+            }
+        }
+        if let Some(min_confirmations_override) = request.min_confirmations_override {
+            if min_confirmations_override as u64 > self.num_voting_members() + self.committees.len()
+            {
+                problems.push((
+                    MultiSigError::InvalidArgument,
+                    "Confirmation override exceeds the number of members who can confirm",
+                ));
+            }
+        }
+        if request.tag.as_ref().map_or(0, |tag| tag.len()) > MAX_TAG_LEN {
+            problems.push((MultiSigError::InvalidArgument, "Tag is too long"));
+        }
+        if let Some(require_memo_above) = self.require_memo_above {
+            if request.actions.iter().any(|action| match action {
+                MultiSigRequestAction::Transfer { amount, memo } => {
+                    amount.0 > require_memo_above && memo.as_deref().unwrap_or("").is_empty()
+                }
+                _ => false,
+            }) {
+                problems.push((
+                    MultiSigError::InvalidArgument,
+                    "Transfers above the memo threshold require a memo",
+                ));
+            }
+        }
+        if self.observers.contains(member) {
+            problems.push((MultiSigError::Observer, "Observers cannot act"));
+        }
+        if self.paused && request.receiver_id != env::current_account_id() {
+            problems.push((MultiSigError::Paused, "Multisig is paused"));
+        }
+        if self.get_num_requests_per_member(member.clone()) >= self.active_requests_limit {
+            problems.push((
+                MultiSigError::TooManyRequests,
+                "Account has too many active requests. Confirm or delete some.",
+            ));
+        }
+        if let Some(max_total_requests) = self.max_total_requests {
+            if self.requests.len() >= max_total_requests as u64 {
+                problems.push((
+                    MultiSigError::TooManyRequests,
+                    "Global request limit reached",
+                ));
+            }
+        }
+        if self.min_request_interval > 0 {
+            if let Some(last) = self.last_request_timestamp.get(&member.to_string()) {
+                if env::block_timestamp() < last + self.min_request_interval {
+                    problems.push((MultiSigError::RateLimited, "Rate limited"));
+                }
+            }
+        }
+        for kind in self.rate_limited_kinds(request) {
+            if let (Some(cooldown), Some(last)) = (
+                self.action_kind_cooldowns.get(&kind),
+                self.action_kind_last_added.get(&kind),
+            ) {
+                if env::block_timestamp() < last + cooldown {
+                    problems.push((MultiSigError::RateLimited, "Action kind is rate limited"));
+                    break;
+                }
+            }
+        }
+        // scanning the queue is only paid for when duplicates are rejected
+        if self.reject_duplicates && self.has_pending_duplicate(request) {
+            problems.push((MultiSigError::DuplicateRequest, "Duplicate request exists"));
+        }
+        if self.reject_replays && self.was_executed_recently(request) {
+            problems.push((
+                MultiSigError::DuplicateRequest,
+                "Identical request was executed recently",
+            ));
+        }
+        problems
+    }
+
+    /// Returns kinds of the request's actions that have a cooldown.
+    fn rate_limited_kinds(&self, request: &MultiSigRequest) -> Vec<String> {
+        if self.action_kind_cooldowns.is_empty() {
+            return vec![];
+        }
+        let mut kinds: Vec<String> = request
+            .actions
+            .iter()
+            .map(|action| action.kind().to_string())
+            .filter(|kind| self.action_kind_cooldowns.get(kind).is_some())
+            .collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
     }
 
     /// Add request for multisig and confirm with the pk that added.
@@ -207,31 +1923,177 @@ impl MultiSigContract {
     }
 
     /// Remove given request and associated confirmations.
+    /// The member who created the request can delete it right away, others have to wait for the cooldown.
     pub fn delete_request(&mut self, request_id: RequestId) {
         self.assert_valid_request(request_id);
         let request_with_signer = self
             .requests
             .get(&request_id)
-            .unwrap_or_else(|| env::panic_str("No such request"));
-        // can't delete requests before 15min
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"));
         assert(
-            env::block_timestamp() > request_with_signer.added_timestamp + REQUEST_COOLDOWN,
+            self.can_delete(self.current_member().as_ref(), &request_with_signer),
+            MultiSigError::Cooldown,
             "Request cannot be deleted immediately after creation.",
         );
         self.remove_request(request_id);
     }
 
-    fn execute_request(&mut self, request: MultiSigRequest) -> PromiseOrValue<bool> {
+    /// Returns true if given member can delete the request.
+    fn can_delete(
+        &self,
+        member: Option<&MultisigMember>,
+        request_with_signer: &MultiSigRequestWithSigner,
+    ) -> bool {
+        // can't delete other member's requests before 15min
+        member == Some(&request_with_signer.member)
+            || env::block_timestamp() > request_with_signer.added_timestamp + REQUEST_COOLDOWN
+    }
+
+    /// Moves membership of the calling account member to `new_account_id`, as approved by an
+    /// `ApproveAccountMigration` request. Outstanding requests, confirmations,
+    /// notes and confirmation activity move along.
+    pub fn migrate_my_account(&mut self, new_account_id: AccountId) {
+        let account_id = env::predecessor_account_id();
+        assert(
+            !self.is_membership_locked(),
+            MultiSigError::MembershipLocked,
+            "Membership locked",
+        );
+        assert(
+            self.account_migrations.get(&account_id) == Some(new_account_id.clone()),
+            MultiSigError::InvalidArgument,
+            "Migration to this account was not approved",
+        );
+        let old_member = MultisigMember::Account { account_id };
+        let new_member = MultisigMember::Account {
+            account_id: new_account_id,
+        };
+        assert(
+            self.members.contains(&old_member),
+            MultiSigError::NotMember,
+            "Caller is not a member of this multisig",
+        );
+        assert(
+            !self.members.contains(&new_member),
+            MultiSigError::InvalidConfig,
+            "New account is already a member",
+        );
+        if let MultisigMember::Account { account_id } = &old_member {
+            self.account_migrations.remove(account_id);
+        }
+        let (old_id, new_id) = (old_member.to_string(), new_member.to_string());
+        self.members.remove(&old_member);
+        self.members.insert(&new_member);
+        if self.observers.remove(&old_member) {
+            self.observers.insert(&new_member);
+        }
+        if let Some(num_requests) = self.num_requests_pk.remove(&old_id) {
+            self.num_requests_pk.insert(&new_id, &num_requests);
+        }
+        if let Some(client_nonces) = self.client_nonces.remove(&old_id) {
+            self.client_nonces.insert(&new_id, &client_nonces);
+        }
+        if let Some(timestamp) = self.last_request_timestamp.remove(&old_id) {
+            self.last_request_timestamp.insert(&new_id, &timestamp);
+        }
+        if let Some(timestamp) = self.member_added_timestamp.remove(&old_id) {
+            self.member_added_timestamp.insert(&new_id, &timestamp);
+        }
+        if let Some(receivers) = self.member_receivers.remove(&old_id) {
+            self.member_receivers.insert(&new_id, &receivers);
+        }
+        if let Some(weight) = self.member_weights.remove(&old_id) {
+            self.member_weights.insert(&new_id, &weight);
+        }
+        let activity: Vec<((String, String), u32)> = self
+            .member_activity
+            .iter()
+            .filter(|((member_id, _), _)| member_id == &old_id)
+            .collect();
+        for ((_, kind), count) in activity {
+            self.member_activity.remove(&(old_id.clone(), kind.clone()));
+            self.member_activity.insert(&(new_id.clone(), kind), &count);
+        }
+        let request_ids: Vec<RequestId> = self.requests.keys().collect();
+        for request_id in request_ids {
+            let mut request_with_signer = self
+                .requests
+                .get(&request_id)
+                .unwrap_or_else(|| panic_error(MultiSigError::Internal, "Request must exist"));
+            if request_with_signer.member == old_member {
+                request_with_signer.member = new_member.clone();
+                self.requests.insert(&request_id, &request_with_signer);
+            }
+            if let Some(mut confirmations) = self.confirmations.get(&request_id) {
+                if let Some(confirmed_at) = confirmations.remove(&old_id) {
+                    confirmations.insert(new_id.clone(), confirmed_at);
+                    self.confirmations.insert(&request_id, &confirmations);
+                }
+            }
+            if let Some(mut notes) = self.request_notes.get(&request_id) {
+                if notes.iter().any(|(member_id, _)| member_id == &old_id) {
+                    for (member_id, _) in notes.iter_mut().filter(|(id, _)| id == &old_id) {
+                        *member_id = new_id.clone();
+                    }
+                    self.request_notes.insert(&request_id, &notes);
+                }
+            }
+        }
+    }
+
+    fn execute_request(
+        &mut self,
+        request_id: RequestId,
+        request: MultiSigRequest,
+        mut code: Option<Vec<u8>>,
+    ) -> PromiseOrValue<bool> {
         let mut promise = Promise::new(request.receiver_id.clone());
         let receiver_id = request.receiver_id.clone();
         let num_actions = request.actions.len();
+        let changes_members = request.actions.iter().any(|action| {
+            matches!(
+                action,
+                MultiSigRequestAction::AddMember { .. }
+                    | MultiSigRequestAction::DeleteMember { .. }
+                    | MultiSigRequestAction::DeleteMemberByKey { .. }
+                    | MultiSigRequestAction::ReplaceAllMembers { .. }
+                    | MultiSigRequestAction::AddObserver { .. }
+                    | MultiSigRequestAction::RemoveObserver { .. }
+            )
+        });
+        self.assert_membership_changes(&request.actions);
+        self.assert_membership_unlocked(&request.actions);
         for action in request.actions {
             promise = match action {
-                MultiSigRequestAction::Transfer { amount } => promise.transfer(amount.into()),
+                MultiSigRequestAction::Transfer { amount, memo: _ } => {
+                    promise.transfer(amount.into())
+                }
                 MultiSigRequestAction::CreateAccount => promise.create_account(),
                 MultiSigRequestAction::DeployContract { code } => {
                     promise.deploy_contract(code.into())
                 }
+                MultiSigRequestAction::DeployContractHash {
+                    code_hash,
+                    code_size,
+                } => {
+                    let code = code.take().unwrap_or_else(|| {
+                        panic_error(
+                            MultiSigError::CodeMismatch,
+                            "Contract code must be supplied with the final confirmation",
+                        )
+                    });
+                    assert(
+                        code.len() as u64 == code_size.0,
+                        MultiSigError::CodeMismatch,
+                        "Contract code size doesn't match the committed size",
+                    );
+                    assert(
+                        env::sha256(&code) == code_hash.0,
+                        MultiSigError::CodeMismatch,
+                        "Contract code hash doesn't match the committed hash",
+                    );
+                    promise.deploy_contract(code)
+                }
                 MultiSigRequestAction::AddMember { member } => {
                     self.assert_self_request(receiver_id.clone());
                     self.add_member(promise, member)
@@ -240,6 +2102,108 @@ impl MultiSigContract {
                     self.assert_self_request(receiver_id.clone());
                     self.delete_member(promise, member)
                 }
+                MultiSigRequestAction::DeleteMemberByKey { public_key } => {
+                    self.assert_self_request(receiver_id.clone());
+                    self.delete_member(promise, MultisigMember::AccessKey { public_key })
+                }
+                MultiSigRequestAction::AddObserver { member } => {
+                    self.assert_self_request(receiver_id.clone());
+                    assert(
+                        !self.members.contains(&member),
+                        MultiSigError::InvalidConfig,
+                        "Observer is already a member of the multisig",
+                    );
+                    self.members.insert(&member);
+                    self.observers.insert(&member);
+                    promise
+                }
+                MultiSigRequestAction::RemoveObserver { member } => {
+                    self.assert_self_request(receiver_id.clone());
+                    assert(
+                        self.observers.remove(&member),
+                        MultiSigError::InvalidConfig,
+                        "No such observer",
+                    );
+                    self.members.remove(&member);
+                    promise
+                }
+                MultiSigRequestAction::CreateCommittee {
+                    name,
+                    members,
+                    threshold,
+                } => {
+                    self.assert_self_request(receiver_id.clone());
+                    self.create_committee(promise, name, members, threshold)
+                }
+                MultiSigRequestAction::DissolveCommittee { name } => {
+                    self.assert_self_request(receiver_id.clone());
+                    self.dissolve_committee(promise, name)
+                }
+                MultiSigRequestAction::SetMemberWeight { member, weight } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        self.members.contains(&member),
+                        MultiSigError::NotMember,
+                        "Only members can have a weight",
+                    );
+                    assert(
+                        weight >= 1,
+                        MultiSigError::InvalidConfig,
+                        "Member weight must be at least 1",
+                    );
+                    if weight == 1 {
+                        self.member_weights.remove(&member.to_string());
+                    } else {
+                        self.member_weights.insert(&member.to_string(), &weight);
+                    }
+                    self.assert_weight_threshold_reachable();
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetQuorumPolicy {
+                    quorum_policy,
+                    weight_threshold,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.quorum_policy = quorum_policy;
+                    self.weight_threshold = weight_threshold;
+                    self.assert_weight_threshold_reachable();
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetMemberReceivers { member, receivers } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        self.members.contains(&member),
+                        MultiSigError::NotMember,
+                        "Only members can have their receivers restricted",
+                    );
+                    if receivers.is_empty() {
+                        self.member_receivers.remove(&member.to_string());
+                    } else {
+                        self.member_receivers
+                            .insert(&member.to_string(), &receivers.into_iter().collect());
+                    }
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::ApproveAccountMigration {
+                    account_id,
+                    new_account_id,
+                } => {
+                    self.assert_self_request(receiver_id.clone());
+                    assert(
+                        self.members.contains(&MultisigMember::Account {
+                            account_id: account_id.clone(),
+                        }),
+                        MultiSigError::NotMember,
+                        "Only account members can migrate",
+                    );
+                    self.account_migrations.insert(&account_id, &new_account_id);
+                    promise
+                }
+                MultiSigRequestAction::CancelMemberRequests { member } => {
+                    self.assert_self_request(receiver_id.clone());
+                    self.cancel_member_requests(&member);
+                    promise
+                }
                 MultiSigRequestAction::AddKey {
                     public_key,
                     permission,
@@ -265,605 +2229,8298 @@ impl MultiSigContract {
                     args,
                     deposit,
                     gas,
-                } => promise.function_call(
-                    method_name,
-                    args.into(),
-                    deposit.into(),
-                    Gas::from(gas.0),
-                ),
+                } => {
+                    if receiver_id == env::current_account_id() {
+                        assert(
+                            !self.self_call_denylist.contains(&method_name),
+                            MultiSigError::MethodDenied,
+                            "Method is not allowed to be called on this contract",
+                        );
+                    }
+                    if let Some(max_deposit) = self.max_function_call_deposit {
+                        assert(
+                            deposit.0 <= max_deposit,
+                            MultiSigError::InvalidArgument,
+                            "Function call deposit exceeds the maximum",
+                        );
+                    }
+                    if let Some(max_gas) = self.max_function_call_gas {
+                        assert(
+                            gas.0 <= max_gas.0,
+                            MultiSigError::InvalidArgument,
+                            "Function call gas exceeds the maximum",
+                        );
+                    }
+                    promise.function_call(
+                        method_name,
+                        args.into(),
+                        deposit.into(),
+                        Gas::from(gas.0),
+                    )
+                }
+                MultiSigRequestAction::NftTransfer {
+                    contract_id,
+                    token_id,
+                    receiver_id: token_receiver_id,
+                    approval_id,
+                    memo,
+                } => {
+                    assert(
+                        contract_id == receiver_id,
+                        MultiSigError::ReceiverMismatch,
+                        "NftTransfer contract_id must be equal to the request receiver_id",
+                    );
+                    promise.function_call(
+                        "nft_transfer".to_string(),
+                        json!({
+                            "receiver_id": token_receiver_id,
+                            "token_id": token_id,
+                            "approval_id": approval_id.map(|id| id.0),
+                            "memo": memo,
+                        })
+                        .to_string()
+                        .into_bytes(),
+                        ONE_YOCTO,
+                        self.default_call_gas,
+                    )
+                }
+                MultiSigRequestAction::FtTransfer {
+                    token_id,
+                    receiver_id: token_receiver_id,
+                    amount,
+                    memo,
+                } => {
+                    assert(
+                        token_id == receiver_id,
+                        MultiSigError::ReceiverMismatch,
+                        "FtTransfer token_id must be equal to the request receiver_id",
+                    );
+                    promise.function_call(
+                        "ft_transfer".to_string(),
+                        json!({
+                            "receiver_id": token_receiver_id,
+                            "amount": amount,
+                            "memo": memo,
+                        })
+                        .to_string()
+                        .into_bytes(),
+                        ONE_YOCTO,
+                        self.default_call_gas,
+                    )
+                }
+                MultiSigRequestAction::SweepTransfer {
+                    receiver_id: sweep_receiver_id,
+                    reserve,
+                } => {
+                    assert(
+                        sweep_receiver_id == receiver_id,
+                        MultiSigError::ReceiverMismatch,
+                        "SweepTransfer receiver_id must be equal to the request receiver_id",
+                    );
+                    let balance = env::account_balance();
+                    assert(
+                        reserve.0 <= balance,
+                        MultiSigError::InsufficientBalance,
+                        "Reserve exceeds the account balance",
+                    );
+                    promise.transfer(balance - reserve.0)
+                }
+                MultiSigRequestAction::ProxyRequest {
+                    multisig_account,
+                    request,
+                } => {
+                    assert(
+                        multisig_account == receiver_id,
+                        MultiSigError::ReceiverMismatch,
+                        "ProxyRequest multisig_account must be equal to the request receiver_id",
+                    );
+                    promise.function_call(
+                        "add_request".to_string(),
+                        json!({ "request": request }).to_string().into_bytes(),
+                        0,
+                        self.default_call_gas,
+                    )
+                }
                 // the following methods must be a single action
                 MultiSigRequestAction::SetNumConfirmations { num_confirmations } => {
                     self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        num_confirmations >= 1
+                            && num_confirmations as u64 <= self.num_voting_members(),
+                        MultiSigError::InvalidConfig,
+                        "Number of confirmations must be between 1 and the number of members",
+                    );
                     self.num_confirmations = num_confirmations;
                     return PromiseOrValue::Value(true);
                 }
+                MultiSigRequestAction::SetConfirmationPercentage {
+                    confirmation_percentage,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    match confirmation_percentage {
+                        Some(percentage) => assert(
+                            (1..=100).contains(&percentage),
+                            MultiSigError::InvalidConfig,
+                            "Confirmation percentage must be between 1 and 100",
+                        ),
+                        None => assert(
+                            self.num_confirmations as u64 <= self.num_voting_members(),
+                            MultiSigError::InvalidConfig,
+                            "Number of confirmations exceeds the number of members",
+                        ),
+                    }
+                    self.confirmation_percentage = confirmation_percentage;
+                    return PromiseOrValue::Value(true);
+                }
                 MultiSigRequestAction::SetActiveRequestsLimit {
                     active_requests_limit,
                 } => {
                     self.assert_one_action_only(receiver_id, num_actions);
+                    let max_requests = self
+                        .get_request_counts()
+                        .into_iter()
+                        .map(|(_, num_requests)| num_requests)
+                        .max()
+                        .unwrap_or(0);
+                    if active_requests_limit < max_requests {
+                        assert(
+                            !self.strict_active_requests_limit,
+                            MultiSigError::InvalidConfig,
+                            "Active requests limit is below the active requests of a member",
+                        );
+                        log_event(
+                            "active_requests_limit_below_usage",
+                            json!({
+                                "active_requests_limit": active_requests_limit,
+                                "max_requests": max_requests,
+                            }),
+                        );
+                    }
                     self.active_requests_limit = active_requests_limit;
                     return PromiseOrValue::Value(true);
                 }
-            };
-        }
-        promise.into()
-    }
-
-    /// Confirm given request with given signing key.
-    /// If with this, there has been enough confirmation, a promise with request will be scheduled.
-    pub fn confirm(&mut self, request_id: RequestId) -> PromiseOrValue<bool> {
-        self.assert_valid_request(request_id);
-        let member = self
-            .current_member()
-            .unwrap_or_else(|| env::panic_str("Must be validated above"));
-        let mut confirmations = self.confirmations.get(&request_id).unwrap();
-        assert(
-            !confirmations.contains(&member.to_string()),
-            "Already confirmed this request with this key",
-        );
-        if confirmations.len() as u32 + 1 >= self.num_confirmations {
-            let request = self.remove_request(request_id);
-            /********************************
-            NOTE: If the tx execution fails for any reason, the request and confirmations are removed already, so the client has to start all over
-            ********************************/
-            self.execute_request(request)
-        } else {
-            confirmations.insert(member.to_string());
-            self.confirmations.insert(&request_id, &confirmations);
-            PromiseOrValue::Value(true)
-        }
-    }
-
-    /********************************
-    Helper methods
-    ********************************/
-
-    /// Returns current member: either predecessor as account or if it's the same as current account - signer.
-    fn current_member(&self) -> Option<MultisigMember> {
-        let member = if env::current_account_id() == env::predecessor_account_id() {
-            MultisigMember::AccessKey {
-                public_key: env::signer_account_pk()
-                    .try_into()
-                    .unwrap_or_else(|_| env::panic_str("Failed to deserialize public key")),
-            }
-        } else {
-            MultisigMember::Account {
-                account_id: env::predecessor_account_id(),
-            }
-        };
-        if self.members.contains(&member) {
-            Some(member)
-        } else {
-            None
-        }
-    }
-
-    /// Add member to the list. Adds access key if member is key based.
-    fn add_member(&mut self, promise: Promise, member: MultisigMember) -> Promise {
-        self.members.insert(&member.clone().into());
-        match member {
-            MultisigMember::AccessKey { public_key } => promise.add_access_key(
-                public_key.into(),
-                DEFAULT_ALLOWANCE,
-                env::current_account_id(),
-                MULTISIG_METHOD_NAMES.to_string(),
-            ),
-            MultisigMember::Account { account_id: _ } => promise,
-        }
-    }
+                MultiSigRequestAction::SetCollectEarlyConfirmations {
+                    collect_early_confirmations,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.collect_early_confirmations = collect_early_confirmations;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetMaxTotalRequests { max_total_requests } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.max_total_requests = max_total_requests;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetRequireIndependentConfirmation {
+                    require_independent_confirmation,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.require_independent_confirmation = require_independent_confirmation;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetPaused { paused } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.paused = paused;
+                    if !paused {
+                        self.consecutive_failures = 0;
+                    }
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetFailurePauseThreshold {
+                    failure_pause_threshold,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.failure_pause_threshold = failure_pause_threshold;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetDecommissionBeneficiary { beneficiary_id } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.decommission_beneficiary = beneficiary_id;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetRequireMemoAbove { require_memo_above } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.require_memo_above = require_memo_above.map(|a| a.0);
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetMaxContractCodeSize {
+                    max_contract_code_size,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.max_contract_code_size = max_contract_code_size.0;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetAutoPrune {
+                    auto_prune,
+                    prune_after,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.auto_prune = auto_prune;
+                    self.prune_after = prune_after.0;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetStrictActiveRequestsLimit {
+                    strict_active_requests_limit,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.strict_active_requests_limit = strict_active_requests_limit;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetRejectDuplicates { reject_duplicates } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.reject_duplicates = reject_duplicates;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetRequestNonceFloor {
+                    request_nonce_floor,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        request_nonce_floor >= self.request_nonce_floor,
+                        MultiSigError::InvalidConfig,
+                        "Request nonce floor can only be increased",
+                    );
+                    self.request_nonce_floor = request_nonce_floor;
+                    self.request_nonce = self.request_nonce.max(request_nonce_floor);
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetDefaultCallGas { default_call_gas } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.default_call_gas = Gas::from(default_call_gas.0);
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetSelfCallDenylist { method_names } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.self_call_denylist = method_names;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetMinRequestInterval {
+                    min_request_interval,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.min_request_interval = min_request_interval.0;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetActionKindCooldown { kind, cooldown } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    match cooldown {
+                        Some(cooldown) => {
+                            self.action_kind_cooldowns.insert(&kind, &cooldown.0);
+                        }
+                        None => {
+                            self.action_kind_cooldowns.remove(&kind);
+                            self.action_kind_last_added.remove(&kind);
+                        }
+                    }
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetConfirmationTtl { confirmation_ttl } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        confirmation_ttl.0 <= MAX_CONFIRMATION_TTL,
+                        MultiSigError::InvalidConfig,
+                        "Confirmation ttl must be at most a year",
+                    );
+                    self.confirmation_ttl = confirmation_ttl.0;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetNewMemberCooldown {
+                    new_member_cooldown,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        new_member_cooldown.0 <= MAX_NEW_MEMBER_COOLDOWN,
+                        MultiSigError::InvalidConfig,
+                        "New member cooldown must be at most a year",
+                    );
+                    self.new_member_cooldown = new_member_cooldown.0;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::ReplaceAllMembers {
+                    members,
+                    num_confirmations,
+                } => {
+                    self.assert_one_action_only(receiver_id.clone(), num_actions);
+                    self.replace_all_members(promise, members, num_confirmations)
+                }
+                MultiSigRequestAction::LockMembership { until } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        until.0 >= self.membership_locked_until,
+                        MultiSigError::InvalidArgument,
+                        "Membership lock can only be extended",
+                    );
+                    self.membership_locked_until = until.0;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetMaxFunctionCallDeposit {
+                    max_function_call_deposit,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.max_function_call_deposit = max_function_call_deposit.map(|d| d.0);
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetMaxFunctionCallGas {
+                    max_function_call_gas,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.max_function_call_gas = max_function_call_gas.map(|g| Gas::from(g.0));
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetRejectReplays {
+                    reject_replays,
+                    replay_window,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.reject_replays = reject_replays;
+                    self.replay_window = replay_window.0;
+                    if !reject_replays {
+                        self.executed_request_hashes.clear();
+                    }
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetLabel { label } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    assert(
+                        label.as_ref().map_or(0, |label| label.len()) <= MAX_LABEL_LEN,
+                        MultiSigError::InvalidArgument,
+                        "Label is too long",
+                    );
+                    self.label = label;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::SetRetryFailedExecutions {
+                    retry_failed_executions,
+                } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    self.retry_failed_executions = retry_failed_executions;
+                    return PromiseOrValue::Value(true);
+                }
+                MultiSigRequestAction::DecommissionContract { beneficiary_id } => {
+                    self.assert_one_action_only(receiver_id.clone(), num_actions);
+                    let beneficiary_id =
+                        beneficiary_id.or_else(|| self.decommission_beneficiary.clone());
+                    self.decommission(promise, beneficiary_id)
+                }
+                MultiSigRequestAction::MultiTransfer { transfers } => {
+                    self.assert_one_action_only(receiver_id.clone(), num_actions);
+                    transfers
+                        .into_iter()
+                        .fold(promise, |promise, (account_id, amount)| {
+                            promise.and(Promise::new(account_id).transfer(amount.0))
+                        })
+                }
+                MultiSigRequestAction::RefreshKeyAllowances => {
+                    self.assert_self_request(receiver_id.clone());
+                    self.refresh_key_allowances(promise)
+                }
+                MultiSigRequestAction::RepairInvariants { from_index, limit } => {
+                    self.assert_one_action_only(receiver_id, num_actions);
+                    for request_id in self.orphaned_confirmations(from_index, limit as u64) {
+                        self.confirmations.remove(&request_id);
+                    }
+                    for request_id in self.requests_without_confirmations(from_index, limit as u64)
+                    {
+                        self.confirmations.insert(&request_id, &HashMap::new());
+                    }
+                    return PromiseOrValue::Value(true);
+                }
+            };
+        }
+        // removed members take their weight with them
+        if changes_members {
+            self.assert_weight_threshold_reachable();
+        }
+        promise
+            .then(ext_self::on_execute(
+                request_id,
+                receiver_id,
+                env::current_account_id(),
+                0,
+                ON_EXECUTE_GAS,
+            ))
+            .into()
+    }
 
-    /// Delete member from the list. Removes access key if the member is key based.
-    fn delete_member(&mut self, promise: Promise, member: MultisigMember) -> Promise {
+    /// Callback after executing the request, logs whether the execution succeeded.
+    /// On failure logs the index of the first failed promise result. Actions of a request
+    /// run as a single batch, which is atomic, so none of them landed if it failed.
+    /// Re-queues failed requests with no confirmations if `retry_failed_executions` was set.
+    /// Pauses the multisig once `failure_pause_threshold` executions failed in a row.
+    #[private]
+    pub fn on_execute(&mut self, request_id: RequestId, receiver_id: AccountId) -> bool {
+        let first_failed_index = (0..env::promise_results_count())
+            .find(|&i| !matches!(env::promise_result(i), PromiseResult::Successful(_)));
+        let executed_request = self.executing_requests.remove(&request_id);
+        if first_failed_index.is_none() {
+            self.consecutive_failures = 0;
+            log_event(
+                "request_executed",
+                json!({ "request_id": request_id, "receiver_id": receiver_id }),
+            );
+            return true;
+        }
+        let requeued = match executed_request {
+            // members deleted in the meantime don't get their requests back
+            Some(request_with_signer) if self.members.contains(&request_with_signer.member) => {
+                let member = request_with_signer.member.to_string();
+                let num_requests = self
+                    .num_requests_pk
+                    .get(&member)
+                    .unwrap_or(0)
+                    .saturating_add(1);
+                self.num_requests_pk.insert(&member, &num_requests);
+                self.requests.insert(&request_id, &request_with_signer);
+                self.confirmations.insert(&request_id, &HashMap::new());
+                true
+            }
+            _ => false,
+        };
+        log_event(
+            "request_execution_failed",
+            json!({
+                "request_id": request_id,
+                "receiver_id": receiver_id,
+                "requeued": requeued,
+                "first_failed_index": first_failed_index,
+            }),
+        );
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if !self.paused
+            && self.failure_pause_threshold > 0
+            && self.consecutive_failures >= self.failure_pause_threshold
+        {
+            self.paused = true;
+            log_event(
+                "circuit_breaker_tripped",
+                json!({ "consecutive_failures": self.consecutive_failures }),
+            );
+        }
+        false
+    }
+
+    /// Confirm given request with given signing key.
+    /// If with this, there has been enough confirmation, a promise with request will be scheduled.
+    pub fn confirm(&mut self, request_id: RequestId) -> PromiseOrValue<bool> {
+        self.confirm_request(request_id, None)
+    }
+
+    /// Confirm given request, then delete those of `prune_ids` the caller could delete with `delete_request`.
+    /// Ids of requests that don't exist or are still in their cooldown are skipped.
+    pub fn confirm_and_prune(
+        &mut self,
+        request_id: RequestId,
+        prune_ids: Vec<RequestId>,
+    ) -> PromiseOrValue<bool> {
+        let result = self.confirm(request_id);
+        let member = self.current_member();
+        for prune_id in prune_ids {
+            if prune_id == request_id {
+                continue;
+            }
+            if let Some(request_with_signer) = self.requests.get(&prune_id) {
+                if self.can_delete(member.as_ref(), &request_with_signer) {
+                    self.remove_request(prune_id);
+                }
+            }
+        }
+        result
+    }
+
+    /// Confirm given request, attaching a short note like "verified invoice #123".
+    /// Notes are kept until the request is executed or deleted.
+    pub fn confirm_with_note(
+        &mut self,
+        request_id: RequestId,
+        note: String,
+    ) -> PromiseOrValue<bool> {
+        let member = self.current_member().unwrap_or_else(|| {
+            panic_error(
+                MultiSigError::NotMember,
+                "Caller (predecessor or signer) is not a member of this multisig",
+            )
+        });
+        assert(
+            self.requests.get(&request_id).is_some(),
+            MultiSigError::NoRequest,
+            "No such request: either wrong number or already confirmed",
+        );
         assert(
-            self.members.len() - 1 >= self.num_confirmations as u64,
-            "Removing given member will make total number of members below number of confirmations",
+            !note.is_empty() && note.len() <= MAX_NOTE_LEN,
+            MultiSigError::InvalidArgument,
+            "Note must be between 1 and 128 bytes",
         );
-        // delete outstanding requests by public_key
-        let request_ids: Vec<u32> = self
-            .requests
-            .iter()
-            .filter_map(|(k, r)| if r.member == member { Some(k) } else { None })
+        let mut notes = self.request_notes.get(&request_id).unwrap_or_default();
+        assert(
+            notes.len() < MAX_NOTES_PER_REQUEST,
+            MultiSigError::InvalidArgument,
+            "Too many notes for this request",
+        );
+        notes.push((member.to_string(), note));
+        self.request_notes.insert(&request_id, &notes);
+        self.confirm(request_id)
+    }
+
+    /// Logs a `request_snapshot` event for each active request ordered by id, so indexers coming
+    /// online can rebuild the queue. Changes nothing, paginated with `from_index` and `limit` to
+    /// bound gas. Returns the number of events logged.
+    pub fn emit_state_snapshot(&mut self, from_index: u64, limit: u64) -> u64 {
+        assert(
+            self.current_member().is_some(),
+            MultiSigError::NotMember,
+            "Caller (predecessor or signer) is not a member of this multisig",
+        );
+        let mut request_ids: Vec<RequestId> = self.requests.keys().collect();
+        request_ids.sort_unstable();
+        let snapshots: Vec<RequestSnapshot> = request_ids
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|request_id| self.try_get_request_full(request_id))
             .collect();
-        for request_id in request_ids {
-            // remove confirmations for this request
-            self.confirmations.remove(&request_id);
-            self.requests.remove(&request_id);
+        for snapshot in &snapshots {
+            log_event(
+                "request_snapshot",
+                serde_json::to_value(snapshot).unwrap_or_else(|_| {
+                    panic_error(MultiSigError::Internal, "Failed to serialize")
+                }),
+            );
         }
-        // remove num_requests_pk entry for member
-        self.num_requests_pk.remove(&member.to_string());
-        self.members.remove(&member);
-        match member {
-            MultisigMember::AccessKey { public_key } => promise.delete_key(public_key.into()),
-            MultisigMember::Account { account_id: _ } => promise,
+        snapshots.len() as u64
+    }
+
+    /// Confirm given request, supplying contract code for a `DeployContractHash` action.
+    /// The code is only used if this confirmation executes the request.
+    pub fn confirm_with_code(
+        &mut self,
+        request_id: RequestId,
+        code: Base64VecU8,
+    ) -> PromiseOrValue<bool> {
+        self.confirm_request(request_id, Some(code.into()))
+    }
+
+    fn confirm_request(
+        &mut self,
+        request_id: RequestId,
+        code: Option<Vec<u8>>,
+    ) -> PromiseOrValue<bool> {
+        self.assert_threshold_reachable();
+        if self.current_member().is_none() {
+            if let Some(name) = self.caller_committee() {
+                return self.confirm_by_committee(request_id, name, code);
+            }
         }
+        self.assert_valid_request(request_id);
+        let member = self
+            .current_member()
+            .unwrap_or_else(|| panic_error(MultiSigError::Internal, "Must be validated above"));
+        self.confirm_by_member(request_id, member, code)
     }
 
-    /// Removes request, removes confirmations and reduces num_requests_pk - used in delete, delete_key, and confirm
-    fn remove_request(&mut self, request_id: RequestId) -> MultiSigRequest {
-        // remove confirmations for this request
-        self.confirmations.remove(&request_id);
-        // remove the original request
+    /// Confirm given request on behalf of an access key member, submitted by a relayer.
+    /// `signature` is an ed25519 signature by `public_key` of the Borsh-serialized
+    /// `(request_id, request_nonce, current_account_id)`. Each signature confirms once,
+    /// and it stops verifying once the nonce moves on.
+    pub fn confirm_signed(
+        &mut self,
+        request_id: RequestId,
+        public_key: PublicKey,
+        signature: Base64VecU8,
+    ) -> PromiseOrValue<bool> {
+        self.assert_threshold_reachable();
+        let member = self.signing_member(&public_key);
+        assert(
+            self.requests.get(&request_id).is_some(),
+            MultiSigError::NoRequest,
+            "No such request: either wrong number or already confirmed",
+        );
+        let request_nonce = self.request_nonce.max(self.request_nonce_floor);
+        let message = (request_id, request_nonce, env::current_account_id())
+            .try_to_vec()
+            .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize"));
+        assert(
+            verify_signature(&public_key, &message, &signature.0),
+            MultiSigError::InvalidSignature,
+            "Invalid signature for confirmation",
+        );
+        // the message only changes with the nonce, so remember what was confirmed at this one
+        let mut confirmed = match self.signed_confirmations.get(&member.to_string()) {
+            Some((nonce, confirmed)) if nonce == request_nonce => confirmed,
+            _ => vec![],
+        };
+        assert(
+            !confirmed.contains(&request_id),
+            MultiSigError::InvalidSignature,
+            "Signature was already used",
+        );
+        confirmed.push(request_id);
+        self.signed_confirmations
+            .insert(&member.to_string(), &(request_nonce, confirmed));
+        self.confirm_by_member(request_id, member, None)
+    }
+
+    /// Delete given request on behalf of an access key member, submitted by a relayer.
+    /// `signature` is an ed25519 signature by `public_key` of the Borsh-serialized
+    /// `("delete_request", request_id, request_nonce, current_account_id)`, so it can't be used
+    /// for another request, another contract or as a confirmation.
+    pub fn delete_request_signed(
+        &mut self,
+        request_id: RequestId,
+        public_key: PublicKey,
+        signature: Base64VecU8,
+    ) {
+        let member = self.signing_member(&public_key);
         let request_with_signer = self
             .requests
-            .remove(&request_id)
-            .unwrap_or_else(|| env::panic_str("Failed to remove existing element"));
-        // decrement num_requests for original request signer
-        let original_member = request_with_signer.member;
-        let mut num_requests = self
-            .num_requests_pk
-            .get(&original_member.to_string())
-            .unwrap_or(0);
-        // safety check for underrun (unlikely since original_signer_pk must have num_requests_pk > 0)
-        if num_requests > 0 {
-            num_requests = num_requests - 1;
-        }
-        self.num_requests_pk
-            .insert(&original_member.to_string(), &num_requests);
-        // return request
-        request_with_signer.request
+            .get(&request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"));
+        let message = (
+            "delete_request",
+            request_id,
+            self.request_nonce.max(self.request_nonce_floor),
+            env::current_account_id(),
+        )
+            .try_to_vec()
+            .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize"));
+        assert(
+            verify_signature(&public_key, &message, &signature.0),
+            MultiSigError::InvalidSignature,
+            "Invalid signature for deletion",
+        );
+        assert(
+            self.can_delete(Some(&member), &request_with_signer),
+            MultiSigError::Cooldown,
+            "Request cannot be deleted immediately after creation.",
+        );
+        self.remove_request(request_id);
     }
 
-    /// Prevents access to calling requests and make sure request_id is valid - used in delete and confirm
-    fn assert_valid_request(&mut self, request_id: RequestId) {
-        // request must come from key added to contract account
+    /// Returns the access key member with given key, panics if it can't act.
+    fn signing_member(&self, public_key: &PublicKey) -> MultisigMember {
+        let member = MultisigMember::AccessKey {
+            public_key: public_key.clone(),
+        };
         assert(
-            self.current_member().is_some(),
-            "Caller (predecessor or signer) is not a member of this multisig",
+            self.members.contains(&member),
+            MultiSigError::NotMember,
+            "Signing key is not a member of this multisig",
         );
-        // request must exist
+        assert(
+            !self.observers.contains(&member),
+            MultiSigError::Observer,
+            "Observers cannot act",
+        );
+        member
+    }
+
+    /// Panics if the members and committees that can confirm can't reach the threshold.
+    fn assert_threshold_reachable(&self) {
+        assert(
+            self.is_threshold_reachable(),
+            MultiSigError::InvalidConfig,
+            "Number of confirmations exceeds the number of members who can confirm. \
+            The contract is misconfigured: a migration deployed with a full access key is needed to recover",
+        );
+    }
+
+    /// Confirms validated request as the given member, unless it was added too recently.
+    fn confirm_by_member(
+        &mut self,
+        request_id: RequestId,
+        member: MultisigMember,
+        code: Option<Vec<u8>>,
+    ) -> PromiseOrValue<bool> {
+        if self.new_member_cooldown > 0 {
+            let added_timestamp = self
+                .member_added_timestamp
+                .get(&member.to_string())
+                .unwrap_or(0);
+            assert(
+                env::block_timestamp() >= added_timestamp.saturating_add(self.new_member_cooldown),
+                MultiSigError::MemberCooldown,
+                "Member was added too recently to confirm requests",
+            );
+        }
+        if self.require_independent_confirmation {
+            let request_with_signer = self
+                .requests
+                .get(&request_id)
+                .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"));
+            assert(
+                request_with_signer.member != member
+                    || !request_with_signer
+                        .request
+                        .actions
+                        .iter()
+                        .any(|action| action.is_self_modifying())
+                    || !self.get_confirmations(request_id).is_empty(),
+                MultiSigError::NotIndependent,
+                "Another member must confirm this request first",
+            );
+        }
+        self.add_confirmation(request_id, member.to_string(), code)
+    }
+
+    /// Records confirmation of a committee member.
+    /// Once the committee threshold is met, the committee confirms the request as one member.
+    fn confirm_by_committee(
+        &mut self,
+        request_id: RequestId,
+        name: String,
+        code: Option<Vec<u8>>,
+    ) -> PromiseOrValue<bool> {
         assert(
             self.requests.get(&request_id).is_some(),
+            MultiSigError::NoRequest,
             "No such request: either wrong number or already confirmed",
         );
-        // request must have
+        let committee_id = format!("committee:{}", name);
+        assert(
+            !self
+                .confirmations
+                .get(&request_id)
+                .unwrap_or_default()
+                .contains_key(&committee_id),
+            MultiSigError::AlreadyConfirmed,
+            "Committee already confirmed this request",
+        );
+        let committee = self
+            .committees
+            .get(&name)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoCommittee, "No such committee"));
+        let mut progress = self
+            .committee_confirmations
+            .get(&request_id)
+            .unwrap_or_default();
+        let votes = progress.entry(name.clone()).or_default();
         assert(
-            self.confirmations.get(&request_id).is_some(),
-            "Internal error: confirmations mismatch requests",
+            votes.insert(self.caller().to_string()),
+            MultiSigError::AlreadyConfirmed,
+            "Already confirmed this request with this key",
         );
+        let threshold_met = reaches_threshold(votes.len(), 0, committee.threshold);
+        if threshold_met {
+            progress.remove(&name);
+        }
+        if progress.is_empty() {
+            self.committee_confirmations.remove(&request_id);
+        } else {
+            self.committee_confirmations.insert(&request_id, &progress);
+        }
+        if threshold_met {
+            self.add_confirmation(request_id, committee_id, code)
+        } else {
+            PromiseOrValue::Value(true)
+        }
     }
 
-    /// Prevents request from approving tx on another account
-    fn assert_self_request(&mut self, receiver_id: AccountId) {
+    /// Adds confirmation of the given member id, executing the request if it's the last one needed.
+    fn add_confirmation(
+        &mut self,
+        request_id: RequestId,
+        member_id: String,
+        code: Option<Vec<u8>>,
+    ) -> PromiseOrValue<bool> {
         assert(
-            receiver_id == env::current_account_id(),
-            "This method only works when receiver_id is equal to current_account_id",
+            !matches!(self.requests.get(&request_id), Some(r) if Self::is_expired(&r)),
+            MultiSigError::Expired,
+            "Request expired",
+        );
+        // requests added while a single confirmation was needed have no entry yet
+        let mut confirmations = self.confirmations.get(&request_id).unwrap_or_default();
+        // expired confirmations are dropped, so their members can confirm again
+        confirmations.retain(|_, confirmed_at| self.is_confirmation_active(*confirmed_at));
+        assert(
+            !confirmations.contains_key(&member_id),
+            MultiSigError::AlreadyConfirmed,
+            "Already confirmed this request with this key",
+        );
+        let active = self.is_request_active(request_id);
+        assert(
+            active || self.collect_early_confirmations,
+            MultiSigError::NotYetActive,
+            "Request not yet active",
         );
+        self.record_member_activity(&member_id, request_id);
+        if active
+            && reaches_threshold(
+                confirmations.len(),
+                1,
+                self.get_effective_threshold(request_id),
+            )
+            && self.reaches_weight_threshold(
+                &confirmations.keys().chain([&member_id]).collect::<Vec<_>>(),
+            )
+        {
+            self.start_execution(request_id, code)
+        } else {
+            // requests confirmed before `not_before` wait for `execute_ready`
+            confirmations.insert(member_id, env::block_timestamp());
+            self.confirmations.insert(&request_id, &confirmations);
+            PromiseOrValue::Value(true)
+        }
     }
 
-    /// Prevents a request from being bundled with other actions
-    fn assert_one_action_only(&mut self, receiver_id: AccountId, num_actions: usize) {
-        self.assert_self_request(receiver_id);
-        assert(num_actions == 1, "This method should be a separate request");
+    /// Counts the kinds of the request's actions towards the confirming member's activity.
+    fn record_member_activity(&mut self, member_id: &str, request_id: RequestId) {
+        let request_with_signer = match self.requests.get(&request_id) {
+            Some(request_with_signer) => request_with_signer,
+            None => return,
+        };
+        for action in &request_with_signer.request.actions {
+            let key = (member_id.to_string(), action.kind().to_string());
+            let count = self.member_activity.get(&key).unwrap_or(0);
+            self.member_activity.insert(&key, &count.saturating_add(1));
+        }
     }
 
-    /********************************
-    View methods
-    ********************************/
+    /// Executes request that got enough confirmations before its `not_before` time.
+    /// `code` is the contract code for a `DeployContractHash` action.
+    pub fn execute_ready(
+        &mut self,
+        request_id: RequestId,
+        code: Option<Base64VecU8>,
+    ) -> PromiseOrValue<bool> {
+        self.assert_valid_request(request_id);
+        assert(
+            self.is_request_active(request_id),
+            MultiSigError::NotYetActive,
+            "Request not yet active",
+        );
+        let confirmations = self.get_confirmations(request_id);
+        assert(
+            reaches_threshold(
+                confirmations.len(),
+                0,
+                self.get_effective_threshold(request_id),
+            ) && self.reaches_weight_threshold(&confirmations.iter().collect::<Vec<_>>()),
+            MultiSigError::InvalidArgument,
+            "Request doesn't have enough confirmations",
+        );
+        self.start_execution(request_id, code.map(Into::into))
+    }
 
-    /// Returns members of the multisig.
-    pub fn get_members(&self) -> Vec<MultisigMember> {
-        self.members.to_vec()
+    /// Returns true if the request's `not_before` time has passed.
+    fn is_request_active(&self, request_id: RequestId) -> bool {
+        match self.requests.get(&request_id) {
+            Some(r) => match r.request.not_before {
+                Some(not_before) => env::block_timestamp() >= not_before.0,
+                None => true,
+            },
+            None => false,
+        }
     }
 
-    pub fn get_request(&self, request_id: RequestId) -> MultiSigRequest {
-        (self
+    /// Removes confirmed request and executes it.
+    fn start_execution(
+        &mut self,
+        request_id: RequestId,
+        code: Option<Vec<u8>>,
+    ) -> PromiseOrValue<bool> {
+        if self.paused {
+            // the multisig itself can still be reconfigured and unpaused
+            assert(
+                self.get_request(request_id).receiver_id == env::current_account_id(),
+                MultiSigError::Paused,
+                "Multisig is paused",
+            );
+        }
+        if let Some(request_with_signer) = self.requests.get(&request_id) {
+            assert(
+                !Self::is_expired(&request_with_signer),
+                MultiSigError::Expired,
+                "Request expired",
+            );
+            // confirmations of requests changing who controls the multisig were given for other members
+            assert(
+                request_with_signer.voting_members_hash == self.voting_members_hash()
+                    || !request_with_signer
+                        .request
+                        .actions
+                        .iter()
+                        .any(|action| action.is_self_modifying()),
+                MultiSigError::MembersChanged,
+                "Members changed since the request was added, it has to be added again",
+            );
+        }
+        if self.retry_failed_executions {
+            if let Some(request_with_signer) = self.requests.get(&request_id) {
+                self.executing_requests
+                    .insert(&request_id, &request_with_signer);
+            }
+        }
+        let request = self.remove_request(request_id);
+        if self.reject_replays {
+            self.record_executed_request(&request);
+        }
+        /********************************
+        NOTE: If the tx execution fails for any reason, the request and confirmations are removed already, so the client has to start all over,
+        unless `retry_failed_executions` is set and `on_execute` re-queues the request.
+        `on_execute` logs a `request_execution_failed` event in that case.
+        ********************************/
+        log_event(
+            "request_execution_started",
+            json!({
+                "request_id": request_id,
+                "receiver_id": request.receiver_id,
+                "num_actions": request.actions.len(),
+            }),
+        );
+        let result = self.execute_request(request_id, request, code);
+        // actions applied right away don't schedule `on_execute`, which would clean up after them
+        if matches!(result, PromiseOrValue::Value(_)) {
+            self.executing_requests.remove(&request_id);
+        }
+        result
+    }
+
+    /// Confirm given request, asserting that the caller resolves to the expected member first.
+    /// Lets relayers and tooling fail loudly instead of confirming under an unexpected identity.
+    pub fn confirm_as(
+        &mut self,
+        request_id: RequestId,
+        expected_member: MultisigMember,
+    ) -> PromiseOrValue<bool> {
+        let member = self.current_member().unwrap_or_else(|| {
+            panic_error(
+                MultiSigError::NotMember,
+                "Caller (predecessor or signer) is not a member of this multisig",
+            )
+        });
+        assert(
+            member == expected_member,
+            MultiSigError::UnexpectedMember,
+            "Caller does not resolve to the expected member",
+        );
+        self.confirm(request_id)
+    }
+
+    /********************************
+    Helper methods
+    ********************************/
+
+    /// Returns current member: either predecessor as account or if it's the same as current account - signer.
+    fn current_member(&self) -> Option<MultisigMember> {
+        let member = self.caller();
+        if self.members.contains(&member) {
+            Some(member)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if confirmation given at `confirmed_at` still counts.
+    fn is_confirmation_active(&self, confirmed_at: u64) -> bool {
+        self.confirmation_ttl == 0
+            || confirmed_at.saturating_add(self.confirmation_ttl) > env::block_timestamp()
+    }
+
+    /// Returns the caller as a member, whether or not it's a member of the multisig.
+    fn caller(&self) -> MultisigMember {
+        if env::current_account_id() == env::predecessor_account_id() {
+            MultisigMember::AccessKey {
+                public_key: env::signer_account_pk().try_into().unwrap_or_else(|_| {
+                    panic_error(MultiSigError::Internal, "Failed to deserialize public key")
+                }),
+            }
+        } else {
+            MultisigMember::Account {
+                account_id: env::predecessor_account_id(),
+            }
+        }
+    }
+
+    /// Returns name of the committee the caller belongs to.
+    fn caller_committee(&self) -> Option<String> {
+        let caller = self.caller();
+        self.committees
+            .iter()
+            .find(|(_, committee)| committee.members.contains(&caller))
+            .map(|(name, _)| name)
+    }
+
+    /// Adds committee. Adds access keys for key based committee members.
+    fn create_committee(
+        &mut self,
+        mut promise: Promise,
+        name: String,
+        members: Vec<MultisigMember>,
+        threshold: u32,
+    ) -> Promise {
+        assert(
+            self.committees.get(&name).is_none(),
+            MultiSigError::InvalidConfig,
+            "Committee already exists",
+        );
+        assert(
+            threshold >= 1 && threshold as usize <= members.len(),
+            MultiSigError::InvalidConfig,
+            "Committee threshold must be between 1 and the number of committee members",
+        );
+        for (i, member) in members.iter().enumerate() {
+            assert(
+                !self.members.contains(member)
+                    && !members[..i].contains(member)
+                    && !self
+                        .committees
+                        .values()
+                        .any(|committee| committee.members.contains(member)),
+                MultiSigError::InvalidConfig,
+                "Committee member must not be a member of the multisig or another committee",
+            );
+            if let MultisigMember::AccessKey { public_key } = member {
+                promise = promise.add_access_key(
+                    public_key.clone(),
+                    DEFAULT_ALLOWANCE,
+                    env::current_account_id(),
+                    MULTISIG_METHOD_NAMES.to_string(),
+                );
+            }
+        }
+        self.committees
+            .insert(&name, &Committee { members, threshold });
+        promise
+    }
+
+    /// Removes committee and its votes that didn't reach the threshold yet.
+    /// Removes access keys of key based committee members.
+    fn dissolve_committee(&mut self, mut promise: Promise, name: String) -> Promise {
+        let committee = self
+            .committees
+            .remove(&name)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoCommittee, "No such committee"));
+        let request_ids: Vec<RequestId> = self.requests.keys().collect();
+        for request_id in request_ids {
+            if let Some(mut progress) = self.committee_confirmations.get(&request_id) {
+                if progress.remove(&name).is_some() {
+                    if progress.is_empty() {
+                        self.committee_confirmations.remove(&request_id);
+                    } else {
+                        self.committee_confirmations.insert(&request_id, &progress);
+                    }
+                }
+            }
+        }
+        for member in committee.members {
+            if let MultisigMember::AccessKey { public_key } = member {
+                promise = promise.delete_key(public_key);
+            }
+        }
+        promise
+    }
+
+    /// Add member to the list. Adds access key if member is key based.
+    /// Observers added as members become voting members.
+    fn add_member(&mut self, promise: Promise, member: MultisigMember) -> Promise {
+        self.observers.remove(&member);
+        self.member_added_timestamp
+            .insert(&member.to_string(), &env::block_timestamp());
+        self.members.insert(&member.clone().into());
+        match &member {
+            MultisigMember::AccessKey { public_key } => {
+                self.member_allowances
+                    .insert(&member.to_string(), &DEFAULT_ALLOWANCE);
+                promise.add_access_key(
+                    public_key.clone(),
+                    DEFAULT_ALLOWANCE,
+                    env::current_account_id(),
+                    MULTISIG_METHOD_NAMES.to_string(),
+                )
+            }
+            MultisigMember::Account { account_id: _ } => promise,
+        }
+    }
+
+    /// Re-adds access keys of access key members with a fresh allowance. Observers have no keys.
+    fn refresh_key_allowances(&mut self, mut promise: Promise) -> Promise {
+        for member in self.members.to_vec() {
+            if let MultisigMember::AccessKey { public_key } = &member {
+                if !self.observers.contains(&member) {
+                    self.member_allowances
+                        .insert(&member.to_string(), &DEFAULT_ALLOWANCE);
+                    promise = promise.delete_key(public_key.clone()).add_access_key(
+                        public_key.clone(),
+                        DEFAULT_ALLOWANCE,
+                        env::current_account_id(),
+                        MULTISIG_METHOD_NAMES.to_string(),
+                    );
+                }
+            }
+        }
+        promise
+    }
+
+    /// Delete member from the list. Removes access key if the member is key based.
+    /// Members count is checked by `assert_membership_changes` for the whole request.
+    fn delete_member(&mut self, promise: Promise, member: MultisigMember) -> Promise {
+        self.cancel_member_requests(&member);
+        self.client_nonces.remove(&member.to_string());
+        self.signed_confirmations.remove(&member.to_string());
+        self.last_request_timestamp.remove(&member.to_string());
+        // observers never had an access key
+        let observer = self.observers.remove(&member);
+        self.member_added_timestamp.remove(&member.to_string());
+        self.member_allowances.remove(&member.to_string());
+        self.member_receivers.remove(&member.to_string());
+        self.member_weights.remove(&member.to_string());
+        self.members.remove(&member);
+        match member {
+            MultisigMember::AccessKey { .. } if observer => promise,
+            MultisigMember::AccessKey { public_key } => promise.delete_key(public_key.into()),
+            MultisigMember::Account { account_id } => {
+                self.account_migrations.remove(&account_id);
+                promise
+            }
+        }
+    }
+
+    /// Replaces members and observers with the given members in a single promise,
+    /// keeping access keys of members that stay. All active requests are removed.
+    fn replace_all_members(
+        &mut self,
+        mut promise: Promise,
+        members: Vec<MultisigMember>,
+        num_confirmations: u32,
+    ) -> Promise {
+        assert(
+            num_confirmations >= 1 && num_confirmations as usize <= members.len(),
+            MultiSigError::InvalidConfig,
+            "Number of confirmations must be between 1 and the number of members",
+        );
+        for (i, member) in members.iter().enumerate() {
+            assert(
+                !members[..i].contains(member)
+                    && !self
+                        .committees
+                        .values()
+                        .any(|committee| committee.members.contains(member)),
+                MultiSigError::InvalidConfig,
+                "Members must be unique and not be members of a committee",
+            );
+        }
+        for member in self.members.to_vec() {
+            if !members.contains(&member) {
+                promise = self.delete_member(promise, member);
+            }
+        }
+        for member in members {
+            // observers become voting members, access key ones get their key only now
+            if !self.members.contains(&member) || self.observers.contains(&member) {
+                promise = self.add_member(promise, member);
+            }
+        }
+        // the electorate changed, so no active request can be confirmed as it was
+        let request_ids: Vec<RequestId> = self.requests.keys().collect();
+        for request_id in request_ids {
+            self.remove_request(request_id);
+        }
+        self.num_confirmations = num_confirmations;
+        promise
+    }
+
+    /// Removes all requests, committees and members with their access keys.
+    /// Transfers the balance not needed for the remaining storage to `beneficiary_id` if given.
+    fn decommission(&mut self, mut promise: Promise, beneficiary_id: Option<AccountId>) -> Promise {
+        let request_ids: Vec<RequestId> = self.requests.keys().collect();
+        for request_id in request_ids {
+            self.remove_request(request_id);
+        }
+        let committee_names: Vec<String> = self.committees.keys().collect();
+        for name in committee_names {
+            promise = self.dissolve_committee(promise, name);
+        }
+        for member in self.members.to_vec() {
+            promise = self.delete_member(promise, member);
+        }
+        match beneficiary_id {
+            Some(beneficiary_id) => {
+                let storage_cost = env::storage_usage() as Balance * env::storage_byte_cost();
+                let balance = env::account_balance().saturating_sub(storage_cost);
+                promise.and(Promise::new(beneficiary_id).transfer(balance))
+            }
+            None => promise,
+        }
+    }
+
+    /// Checks that members added and removed by the request leave at least `num_confirmations` members.
+    /// Only the final state matters, so actions within the request can be in any order.
+    fn assert_membership_changes(&self, actions: &[MultiSigRequestAction]) {
+        // observers don't count, adding one as a member turns it into a voting member
+        let voting_members = || {
+            self.members
+                .iter()
+                .filter(|member| !self.observers.contains(member))
+                .collect::<Vec<_>>()
+        };
+        let mut members: Option<Vec<MultisigMember>> = None;
+        for action in actions {
+            match action {
+                MultiSigRequestAction::AddMember { member } => {
+                    let members = members.get_or_insert_with(voting_members);
+                    if !members.contains(member) {
+                        members.push(member.clone());
+                    }
+                }
+                MultiSigRequestAction::DeleteMember { member } => {
+                    members
+                        .get_or_insert_with(voting_members)
+                        .retain(|m| m != member);
+                }
+                MultiSigRequestAction::DeleteMemberByKey { public_key } => {
+                    members.get_or_insert_with(voting_members).retain(
+                        |m| !matches!(m, MultisigMember::AccessKey { public_key: key } if key == public_key),
+                    );
+                }
+                _ => {}
+            }
+        }
+        if let Some(members) = members {
+            assert(
+                !members.is_empty(),
+                MultiSigError::InvalidConfig,
+                "Removing given members will leave no voting members",
+            );
+            // the number of confirmations follows the members when it's a percentage
+            let required_confirmations = match self.confirmation_percentage {
+                Some(percentage) => percentage_of_members(members.len() as u64, percentage),
+                None => self.num_confirmations,
+            };
+            assert(
+                required_confirmations >= 1 && members.len() >= required_confirmations as usize,
+                MultiSigError::InvalidConfig,
+                "Removing given member will make total number of members below number of confirmations",
+            );
+        }
+    }
+
+    /// Panics if the actions change members while membership is locked.
+    fn assert_membership_unlocked(&self, actions: &[MultiSigRequestAction]) {
+        assert(
+            !self.is_membership_change_locked(actions),
+            MultiSigError::MembershipLocked,
+            "Membership locked",
+        );
+    }
+
+    fn is_membership_locked(&self) -> bool {
+        env::block_timestamp() < self.membership_locked_until
+    }
+
+    /// Returns true if the actions change who confirms requests while membership is locked.
+    fn is_membership_change_locked(&self, actions: &[MultiSigRequestAction]) -> bool {
+        self.is_membership_locked()
+            && actions.iter().any(|action| {
+                matches!(
+                    action,
+                    MultiSigRequestAction::AddMember { .. }
+                        | MultiSigRequestAction::DeleteMember { .. }
+                        | MultiSigRequestAction::DeleteMemberByKey { .. }
+                        | MultiSigRequestAction::ReplaceAllMembers { .. }
+                        | MultiSigRequestAction::CreateCommittee { .. }
+                        | MultiSigRequestAction::DissolveCommittee { .. }
+                        | MultiSigRequestAction::SetMemberWeight { .. }
+                        | MultiSigRequestAction::DecommissionContract { .. }
+                )
+            })
+    }
+
+    /// Problems executing the request would run into regardless of its confirmations.
+    /// Checks that depend on the state at execution time, like balances, aren't covered.
+    fn execution_problems(&self, request: &MultiSigRequest) -> Vec<(MultiSigError, &'static str)> {
+        let mut problems = vec![];
+        let is_self_request = request.receiver_id == env::current_account_id();
+        for action in &request.actions {
+            if action.is_self_only() && !is_self_request {
+                problems.push((
+                    MultiSigError::ReceiverMismatch,
+                    "This method only works when receiver_id is equal to current_account_id",
+                ));
+            }
+            if action.is_single_action() && request.actions.len() > 1 {
+                problems.push((
+                    MultiSigError::SeparateRequest,
+                    "This method should be a separate request",
+                ));
+            }
+            let (target, message) = match action {
+                MultiSigRequestAction::NftTransfer { contract_id, .. } => (
+                    contract_id,
+                    "NftTransfer contract_id must be equal to the request receiver_id",
+                ),
+                MultiSigRequestAction::FtTransfer { token_id, .. } => (
+                    token_id,
+                    "FtTransfer token_id must be equal to the request receiver_id",
+                ),
+                MultiSigRequestAction::SweepTransfer { receiver_id, .. } => (
+                    receiver_id,
+                    "SweepTransfer receiver_id must be equal to the request receiver_id",
+                ),
+                MultiSigRequestAction::ProxyRequest {
+                    multisig_account, ..
+                } => (
+                    multisig_account,
+                    "ProxyRequest multisig_account must be equal to the request receiver_id",
+                ),
+                _ => continue,
+            };
+            if *target != request.receiver_id {
+                problems.push((MultiSigError::ReceiverMismatch, message));
+            }
+        }
+        if self.is_membership_change_locked(&request.actions) {
+            problems.push((MultiSigError::MembershipLocked, "Membership locked"));
+        }
+        problems
+    }
+
+    /// Returns true if members and committees together can give the required confirmations.
+    fn is_threshold_reachable(&self) -> bool {
+        // each committee confirms as one member
+        self.required_confirmations() as u64 <= self.num_voting_members() + self.committees.len()
+    }
+
+    /// Number of members that can confirm requests.
+    fn num_voting_members(&self) -> u64 {
+        self.members.len() - self.observers.len()
+    }
+
+    /// Hash of the sorted members that can confirm requests.
+    fn voting_members_hash(&self) -> Vec<u8> {
+        let mut voting_members: Vec<String> = self
+            .members
+            .iter()
+            .filter(|member| !self.observers.contains(member))
+            .map(|member| member.to_string())
+            .collect();
+        voting_members.sort_unstable();
+        env::sha256(
+            &voting_members
+                .try_to_vec()
+                .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize")),
+        )
+    }
+
+    /// Number of confirmations requests need, `confirmation_percentage` of voting members if set.
+    fn required_confirmations(&self) -> u32 {
+        match self.confirmation_percentage {
+            Some(percentage) => percentage_of_members(self.num_voting_members(), percentage),
+            None => self.num_confirmations,
+        }
+    }
+
+    /// Returns weight of the member with given id, committees weigh 1.
+    fn member_weight(&self, member_id: &str) -> u32 {
+        self.member_weights.get(&member_id.to_string()).unwrap_or(1)
+    }
+
+    /// Returns true if confirmations by given member ids have the combined weight the quorum
+    /// policy requires. Always true under the `Count` policy.
+    fn reaches_weight_threshold(&self, member_ids: &[&String]) -> bool {
+        match self.quorum_policy {
+            QuorumPolicy::Count => true,
+            QuorumPolicy::CountAndWeight => {
+                member_ids
+                    .iter()
+                    .map(|member_id| self.member_weight(member_id) as u64)
+                    .sum::<u64>()
+                    >= self.weight_threshold as u64
+            }
+        }
+    }
+
+    /// Panics if voting members can't reach the weight threshold together.
+    fn assert_weight_threshold_reachable(&self) {
+        if self.quorum_policy == QuorumPolicy::Count {
+            return;
+        }
+        let total_weight: u64 = self
+            .members
+            .iter()
+            .filter(|member| !self.observers.contains(member))
+            .map(|member| self.member_weight(&member.to_string()) as u64)
+            .sum();
+        assert(
+            self.weight_threshold >= 1 && self.weight_threshold as u64 <= total_weight,
+            MultiSigError::InvalidConfig,
+            "Weight threshold must be between 1 and the combined weight of voting members",
+        );
+    }
+
+    /// Returns number of confirmations given request needs, never less than the multisig requires.
+    fn request_threshold(&self, request: &MultiSigRequest) -> u32 {
+        self.required_confirmations()
+            .max(request.min_confirmations_override.unwrap_or(0))
+    }
+
+    /// Removes all outstanding requests of the member and resets its num_requests_pk.
+    fn cancel_member_requests(&mut self, member: &MultisigMember) {
+        // delete outstanding requests by public_key
+        let request_ids: Vec<RequestId> = self
             .requests
-            .get(&request_id)
-            .unwrap_or_else(|| env::panic_str("No such request")))
-        .request
+            .iter()
+            .filter_map(|(k, r)| if &r.member == member { Some(k) } else { None })
+            .collect();
+        for request_id in request_ids {
+            // remove confirmations for this request
+            self.confirmations.remove(&request_id);
+            self.committee_confirmations.remove(&request_id);
+            self.request_notes.remove(&request_id);
+            self.requests.remove(&request_id);
+        }
+        // remove num_requests_pk entry for member
+        self.num_requests_pk.remove(&member.to_string());
+    }
+
+    /// Removes request, removes confirmations and reduces num_requests_pk - used in delete, delete_key, and confirm
+    fn remove_request(&mut self, request_id: RequestId) -> MultiSigRequest {
+        // remove confirmations for this request
+        self.confirmations.remove(&request_id);
+        self.committee_confirmations.remove(&request_id);
+        self.request_notes.remove(&request_id);
+        // remove the original request
+        let request_with_signer = self.requests.remove(&request_id).unwrap_or_else(|| {
+            panic_error(MultiSigError::Internal, "Failed to remove existing element")
+        });
+        // decrement num_requests for original request signer
+        let original_member = request_with_signer.member;
+        // saturating in case of underrun (unlikely since original_signer_pk must have num_requests_pk > 0)
+        let num_requests = self
+            .num_requests_pk
+            .get(&original_member.to_string())
+            .unwrap_or(0)
+            .saturating_sub(1);
+        self.num_requests_pk
+            .insert(&original_member.to_string(), &num_requests);
+        // return request
+        request_with_signer.request
+    }
+
+    /// Message that a member signs to delegate adding the request.
+    fn delegated_request_message(&self, request: &MultiSigRequest) -> Vec<u8> {
+        let mut message = env::current_account_id()
+            .try_to_vec()
+            .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize"));
+        message.extend(
+            self.request_nonce
+                .max(self.request_nonce_floor)
+                .to_le_bytes(),
+        );
+        message.extend(
+            request
+                .try_to_vec()
+                .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize")),
+        );
+        message
+    }
+
+    /// Returns true if a request with the same receiver and actions, memos aside, is already pending.
+    fn has_pending_duplicate(&self, request: &MultiSigRequest) -> bool {
+        let hash = request_hash(request);
+        self.requests
+            .values()
+            .any(|r| request_hash(&r.request) == hash)
+    }
+
+    /// Remembers hash of the executed request, dropping the oldest one past the limit.
+    fn record_executed_request(&mut self, request: &MultiSigRequest) {
+        if self.executed_request_hashes.len() >= MAX_EXECUTED_REQUEST_HASHES {
+            self.executed_request_hashes.remove(0);
+        }
+        self.executed_request_hashes
+            .push((request_hash(request), env::block_timestamp()));
+    }
+
+    /// Returns true if the same request was executed within the replay window.
+    fn was_executed_recently(&self, request: &MultiSigRequest) -> bool {
+        let hash = request_hash(request);
+        self.executed_request_hashes
+            .iter()
+            .any(|(executed_hash, executed_at)| {
+                *executed_hash == hash && env::block_timestamp() < executed_at + self.replay_window
+            })
+    }
+
+    /// Prevents access to calling requests and make sure request_id is valid - used in delete and confirm
+    fn assert_valid_request(&mut self, request_id: RequestId) {
+        // request must come from key added to contract account
+        let member = self.current_member().unwrap_or_else(|| {
+            panic_error(
+                MultiSigError::NotMember,
+                "Caller (predecessor or signer) is not a member of this multisig",
+            )
+        });
+        assert(
+            !self.observers.contains(&member),
+            MultiSigError::Observer,
+            "Observers cannot act",
+        );
+        // request must exist
+        assert(
+            self.requests.get(&request_id).is_some(),
+            MultiSigError::NoRequest,
+            "No such request: either wrong number or already confirmed",
+        );
+    }
+
+    /// Prevents request from approving tx on another account
+    fn assert_self_request(&mut self, receiver_id: AccountId) {
+        assert(
+            receiver_id == env::current_account_id(),
+            MultiSigError::ReceiverMismatch,
+            "This method only works when receiver_id is equal to current_account_id",
+        );
+    }
+
+    /// Prevents a request from being bundled with other actions
+    fn assert_one_action_only(&mut self, receiver_id: AccountId, num_actions: usize) {
+        self.assert_self_request(receiver_id);
+        assert(
+            num_actions == 1,
+            MultiSigError::SeparateRequest,
+            "This method should be a separate request",
+        );
+    }
+
+    /********************************
+    View methods
+    ********************************/
+
+    pub fn get_version(&self) -> String {
+        VERSION.to_string()
+    }
+
+    /// Returns version, number of confirmations and number of members in one call.
+    pub fn get_info(&self) -> ContractInfo {
+        ContractInfo {
+            version: VERSION.to_string(),
+            num_confirmations: self.num_confirmations,
+            num_members: self.members.len(),
+        }
+    }
+
+    pub fn get_defaults(&self) -> Defaults {
+        Defaults {
+            default_allowance: U128(DEFAULT_ALLOWANCE),
+            request_cooldown: U64(REQUEST_COOLDOWN),
+        }
+    }
+
+    /// Returns metrics for monitoring in a single view.
+    pub fn health(&self) -> HealthReport {
+        HealthReport {
+            num_members: self.members.len(),
+            num_confirmations: self.required_confirmations(),
+            num_requests: self.requests.len(),
+            num_deletable_requests: self
+                .requests
+                .values()
+                .filter(|r| env::block_timestamp() > r.added_timestamp + REQUEST_COOLDOWN)
+                .count() as u64,
+            threshold_reachable: self.min_confirming_sets_size() > 0,
+        }
+    }
+
+    /// Returns account balance and locked (staked) balance, as used by `SweepTransfer`.
+    pub fn get_balance(&self) -> (U128, U128) {
+        (
+            U128(env::account_balance()),
+            U128(env::account_locked_balance()),
+        )
+    }
+
+    /// Returns the human readable label of the multisig.
+    pub fn get_label(&self) -> Option<String> {
+        self.label.clone()
+    }
+
+    /// Returns members of the multisig, including observers marked as such.
+    pub fn get_members(&self) -> Vec<MemberWithRole> {
+        self.members
+            .iter()
+            .map(|member| MemberWithRole {
+                observer: self.observers.contains(&member),
+                member,
+            })
+            .collect()
+    }
+
+    /// Returns members with their weight, observer flag, time added and number of active requests.
+    pub fn get_members_detailed(&self) -> Vec<MemberInfo> {
+        self.members
+            .iter()
+            .map(|member| MemberInfo {
+                weight: self.member_weight(&member.to_string()),
+                observer: self.observers.contains(&member),
+                added_timestamp: U64(self
+                    .member_added_timestamp
+                    .get(&member.to_string())
+                    .unwrap_or(0)),
+                num_requests: self.get_num_requests_per_member(member.clone()),
+                member,
+            })
+            .collect()
+    }
+
+    /// Returns members added strictly after given time in nanoseconds.
+    pub fn get_members_added_after(&self, timestamp: U64) -> Vec<MultisigMember> {
+        self.members
+            .iter()
+            .filter(|member| {
+                matches!(
+                    self.member_added_timestamp.get(&member.to_string()),
+                    Some(added) if added > timestamp.0
+                )
+            })
+            .collect()
+    }
+
+    /// Returns receivers the member is restricted to adding requests to, ordered.
+    /// Empty if the member isn't restricted.
+    pub fn get_member_receivers(&self, member: MultisigMember) -> Vec<AccountId> {
+        let mut receivers: Vec<AccountId> = self
+            .member_receivers
+            .get(&member.to_string())
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        receivers.sort();
+        receivers
+    }
+
+    /// Returns members that can only view and can't add or confirm requests.
+    pub fn get_observers(&self) -> Vec<MultisigMember> {
+        self.observers.to_vec()
+    }
+
+    /// Returns either only access key members or only account members.
+    pub fn get_members_by_kind(&self, access_keys: bool) -> Vec<MultisigMember> {
+        self.members
+            .to_vec()
+            .into_iter()
+            .filter(|member| matches!(member, MultisigMember::AccessKey { .. }) == access_keys)
+            .collect()
+    }
+
+    pub fn get_request(&self, request_id: RequestId) -> MultiSigRequest {
+        self.try_get_request(request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"))
+    }
+
+    /// Like `get_request`, but returns `None` for unknown ids instead of panicking.
+    pub fn try_get_request(&self, request_id: RequestId) -> Option<MultiSigRequest> {
+        self.requests.get(&request_id).map(|r| r.request)
+    }
+
+    /// Returns the request with who added it, when, and its confirmations,
+    /// `None` for unknown ids.
+    pub fn try_get_request_full(&self, request_id: RequestId) -> Option<RequestSnapshot> {
+        let request_with_signer = self.requests.get(&request_id)?;
+        let mut confirmations: Vec<(String, U64)> = self
+            .confirmations
+            .get(&request_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(member, confirmed_at)| (member, U64(confirmed_at)))
+            .collect();
+        confirmations.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Some(RequestSnapshot {
+            request_id,
+            member: request_with_signer.member,
+            added_timestamp: U64(request_with_signer.added_timestamp),
+            request: request_with_signer.request,
+            confirmations,
+        })
+    }
+
+    /// Returns the request with its current and required number of confirmations, so contract
+    /// members like DAOs can decide on a confirmation with a single cross-contract view call.
+    pub fn get_request_for_confirmation(
+        &self,
+        request_id: RequestId,
+    ) -> (MultiSigRequest, u32, u32) {
+        let request = self.get_request(request_id);
+        (
+            request,
+            self.get_confirmations(request_id).len() as u32,
+            self.get_effective_threshold(request_id),
+        )
+    }
+
+    /// Returns requests with given ids in order, `None` for ids of requests that aren't active.
+    pub fn get_requests(&self, request_ids: Vec<RequestId>) -> Vec<Option<MultiSigRequest>> {
+        request_ids
+            .into_iter()
+            .map(|request_id| self.requests.get(&request_id).map(|r| r.request))
+            .collect()
+    }
+
+    /// Returns when the request was added, e.g. to compute when its delete cooldown elapses.
+    pub fn get_request_added_timestamp(&self, request_id: RequestId) -> U64 {
+        U64(self
+            .requests
+            .get(&request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"))
+            .added_timestamp)
+    }
+
+    /// Returns how many actions of each kind the member confirmed, ordered by kind.
+    pub fn get_member_activity(&self, member: MultisigMember) -> Vec<(String, u32)> {
+        let member_id = member.to_string();
+        let mut activity: Vec<(String, u32)> = self
+            .member_activity
+            .iter()
+            .filter(|((id, _), _)| id == &member_id)
+            .map(|((_, kind), count)| (kind, count))
+            .collect();
+        activity.sort();
+        activity
+    }
+
+    /// Returns notes confirmers attached to the request, with the member that attached each.
+    pub fn get_request_notes(&self, request_id: RequestId) -> Vec<(String, String)> {
+        self.request_notes.get(&request_id).unwrap_or_default()
+    }
+
+    /// Returns when the request stops being confirmable, `None` if it was added without a TTL.
+    pub fn get_request_expires_at(&self, request_id: RequestId) -> Option<U64> {
+        self.requests
+            .get(&request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"))
+            .expires_at
+            .map(U64)
+    }
+
+    /// Returns the timestamp after which any member can delete the request.
+    pub fn get_delete_eligible_at(&self, request_id: RequestId) -> U64 {
+        U64(self.get_request_added_timestamp(request_id).0 + REQUEST_COOLDOWN)
+    }
+
+    /// Returns nanoseconds left until the request can be deleted by any member, zero if already eligible.
+    pub fn seconds_until_deletable(&self, request_id: RequestId) -> U64 {
+        U64(self
+            .get_delete_eligible_at(request_id)
+            .0
+            .saturating_sub(env::block_timestamp()))
+    }
+
+    /// Returns number of confirmations the request needs to execute: `get_required_confirmations`,
+    /// or the request's `min_confirmations_override` if that's higher.
+    pub fn get_effective_threshold(&self, request_id: RequestId) -> u32 {
+        let request_with_signer = self
+            .requests
+            .get(&request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"));
+        self.request_threshold(&request_with_signer.request)
+    }
+
+    /// Returns true if one more confirmation by a member who hasn't confirmed yet would
+    /// reach the threshold, e.g. for wallets to warn that confirming executes the request.
+    pub fn would_execute(&self, request_id: RequestId) -> bool {
+        reaches_threshold(
+            self.get_confirmations(request_id).len(),
+            1,
+            self.get_effective_threshold(request_id),
+        )
+    }
+
+    /// Returns allowances access key members were added with, by member id. 0 means unlimited.
+    /// The remaining allowance isn't readable on-chain, so this is only what was configured.
+    pub fn get_member_allowances(&self) -> Vec<(String, U128)> {
+        self.member_allowances
+            .iter()
+            .map(|(member, allowance)| (member, U128(allowance)))
+            .collect()
+    }
+
+    /// Returns the form of the member the contract keys its per-member state by,
+    /// e.g. `{"public_key":"ed25519:..."}` or `{"account_id":"alice.near"}`.
+    /// Those are the member ids returned by `get_confirmations`.
+    pub fn normalize_member(&self, member: MultisigMember) -> String {
+        member.to_string()
+    }
+
+    pub fn get_num_requests_per_member(&self, member: MultisigMember) -> u32 {
+        self.num_requests_pk.get(&member.to_string()).unwrap_or(0)
+    }
+
+    /// Returns number of active requests for every member.
+    pub fn get_request_counts(&self) -> Vec<(MultisigMember, u32)> {
+        self.members
+            .iter()
+            .map(|member| {
+                let num_requests = self.num_requests_pk.get(&member.to_string()).unwrap_or(0);
+                (member, num_requests)
+            })
+            .collect()
+    }
+
+    /// Returns ids of active requests originated by given member.
+    pub fn get_requests_by_member(
+        &self,
+        member: MultisigMember,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<RequestId> {
+        self.requests
+            .iter()
+            .filter(|(_, r)| r.member == member)
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(request_id, _)| request_id)
+            .collect()
+    }
+
+    /// Returns ids of active requests with given tag.
+    pub fn get_requests_by_tag(&self, tag: String, from_index: u64, limit: u64) -> Vec<RequestId> {
+        self.requests
+            .iter()
+            .filter(|(_, r)| r.request.tag.as_ref() == Some(&tag))
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(request_id, _)| request_id)
+            .collect()
+    }
+
+    pub fn list_request_ids(&self) -> Vec<RequestId> {
+        self.requests.keys().collect()
+    }
+
+    /// Returns `(request_id, added_timestamp)` of active requests, oldest first.
+    /// Loads and sorts all active requests on every call (O(n log n)), so keep `limit` modest.
+    pub fn list_requests_by_age(&self, from_index: u64, limit: u64) -> Vec<(RequestId, U64)> {
+        let mut requests: Vec<(RequestId, u64)> = self
+            .requests
+            .iter()
+            .map(|(request_id, r)| (request_id, r.added_timestamp))
+            .collect();
+        requests.sort_by_key(|&(request_id, added_timestamp)| (added_timestamp, request_id));
+        requests
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(request_id, added_timestamp)| (request_id, U64(added_timestamp)))
+            .collect()
+    }
+
+    /// Returns members whose confirmations of the request haven't expired.
+    pub fn get_confirmations(&self, request_id: RequestId) -> Vec<String> {
+        self.try_get_confirmations(request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"))
+    }
+
+    /// Like `get_confirmations`, but returns `None` for unknown ids instead of panicking.
+    pub fn try_get_confirmations(&self, request_id: RequestId) -> Option<Vec<String>> {
+        self.requests.get(&request_id)?;
+        Some(
+            self.confirmations
+                .get(&request_id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(_, confirmed_at)| self.is_confirmation_active(*confirmed_at))
+                .map(|(member, _)| member)
+                .collect(),
+        )
+    }
+
+    /// Returns ids of active requests `member` has an unexpired confirmation for, ordered by id.
+    /// Scans all active requests, so page through them with `from_index` and `limit`.
+    pub fn get_confirmations_by_member(
+        &self,
+        member: MultisigMember,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<RequestId> {
+        let member = member.to_string();
+        let mut request_ids: Vec<RequestId> = self.requests.keys().collect();
+        request_ids.sort_unstable();
+        request_ids
+            .into_iter()
+            .filter(|request_id| {
+                let confirmed_at = self
+                    .confirmations
+                    .get(request_id)
+                    .and_then(|confirmations| confirmations.get(&member).copied());
+                matches!(confirmed_at, Some(confirmed_at) if self.is_confirmation_active(confirmed_at))
+            })
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Returns `(request_id, action types)` of active requests ordered by id,
+    /// so pending `AddKey` or `DeployContract` actions can be spotted without loading whole requests.
+    pub fn get_pending_action_kinds(
+        &self,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<(RequestId, Vec<String>)> {
+        let mut request_ids: Vec<RequestId> = self.requests.keys().collect();
+        request_ids.sort_unstable();
+        request_ids
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|request_id| {
+                let request_with_signer = self.requests.get(&request_id)?;
+                let kinds = request_with_signer
+                    .request
+                    .actions
+                    .iter()
+                    .map(|action| action.kind().to_string())
+                    .collect();
+                Some((request_id, kinds))
+            })
+            .collect()
+    }
+
+    /// Returns the configuration together with active requests ordered by id and their confirmations.
+    /// Requests can be large, so they are paginated with `from_index` and `limit`.
+    pub fn export_state(&self, from_index: u64, limit: u64) -> StateSnapshot {
+        let mut request_ids: Vec<RequestId> = self.requests.keys().collect();
+        request_ids.sort_unstable();
+        let requests = request_ids
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|request_id| self.try_get_request_full(request_id))
+            .collect();
+        StateSnapshot {
+            members: self.members.to_vec(),
+            num_confirmations: self.num_confirmations,
+            request_nonce: self.request_nonce,
+            active_requests_limit: self.active_requests_limit,
+            num_requests: self.requests.len(),
+            requests,
+        }
+    }
+
+    /// Returns `(request_id, confirmations, required)` of active requests ordered by id.
+    pub fn get_all_progress(&self, from_index: u64, limit: u64) -> Vec<(RequestId, u32, u32)> {
+        let mut request_ids: Vec<RequestId> = self.requests.keys().collect();
+        request_ids.sort_unstable();
+        request_ids
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|request_id| {
+                (
+                    request_id,
+                    self.get_confirmations(request_id).len() as u32,
+                    self.get_effective_threshold(request_id),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns `(request_id, ready_at)` of requests that already have enough confirmations,
+    /// e.g. confirmed before `not_before` or after the number of confirmations was lowered.
+    /// `ready_at` is the latest of the last confirmation and `not_before`.
+    /// `execute_ready` executes them once `ready_at` has passed.
+    pub fn get_ready_to_execute(&self) -> Vec<(RequestId, U64)> {
+        let mut ready: Vec<(RequestId, U64)> = self
+            .requests
+            .keys()
+            .filter_map(|request_id| {
+                let (confirmed_by, confirmed_at): (Vec<String>, Vec<u64>) = self
+                    .confirmations
+                    .get(&request_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(_, confirmed_at)| self.is_confirmation_active(*confirmed_at))
+                    .unzip();
+                if reaches_threshold(
+                    confirmed_at.len(),
+                    0,
+                    self.get_effective_threshold(request_id),
+                ) && self.reaches_weight_threshold(&confirmed_by.iter().collect::<Vec<_>>())
+                {
+                    let not_before = self
+                        .requests
+                        .get(&request_id)?
+                        .request
+                        .not_before
+                        .map_or(0, |not_before| not_before.0);
+                    let confirmed_at = confirmed_at.into_iter().max().unwrap_or(0);
+                    Some((request_id, U64(confirmed_at.max(not_before))))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        ready.sort_unstable_by_key(|&(request_id, _)| request_id);
+        ready
+    }
+
+    pub fn has_confirmed(&self, request_id: RequestId, member: MultisigMember) -> bool {
+        assert(
+            self.requests.get(&request_id).is_some(),
+            MultiSigError::NoRequest,
+            "No such request",
+        );
+        // requests added while a single confirmation was needed have no entry yet
+        let confirmations = self.confirmations.get(&request_id).unwrap_or_default();
+        match confirmations.get(&member.to_string()) {
+            Some(confirmed_at) => self.is_confirmation_active(*confirmed_at),
+            None => false,
+        }
+    }
+
+    pub fn get_retry_failed_executions(&self) -> bool {
+        self.retry_failed_executions
+    }
+
+    pub fn get_new_member_cooldown(&self) -> U64 {
+        U64(self.new_member_cooldown)
+    }
+
+    pub fn get_confirmation_ttl(&self) -> U64 {
+        U64(self.confirmation_ttl)
+    }
+
+    /// Returns committees by name.
+    pub fn get_committees(&self) -> Vec<(String, Committee)> {
+        self.committees.to_vec()
+    }
+
+    /// Returns the smallest number of members whose confirmations could execute a request.
+    /// Members confirm once each, heaviest first under the `CountAndWeight` policy. Committees
+    /// with the fewest members needed, weighing 1 each, are used only when members alone can't
+    /// reach the thresholds. Returns 0 if they can't be reached at all.
+    pub fn min_confirming_sets_size(&self) -> u32 {
+        let required_confirmations = self.required_confirmations() as u64;
+        let weight_threshold = match self.quorum_policy {
+            QuorumPolicy::Count => 0,
+            QuorumPolicy::CountAndWeight => self.weight_threshold as u64,
+        };
+        let mut weights: Vec<u64> = self
+            .members
+            .iter()
+            .filter(|member| !self.observers.contains(member))
+            .map(|member| self.member_weight(&member.to_string()) as u64)
+            .collect();
+        weights.sort_unstable_by(|a, b| b.cmp(a));
+        let mut num_members: u64 = 0;
+        let mut weight: u64 = 0;
+        for member_weight in weights {
+            if num_members >= required_confirmations && weight >= weight_threshold {
+                break;
+            }
+            num_members += 1;
+            weight += member_weight;
+        }
+        let num_committees = required_confirmations
+            .saturating_sub(num_members)
+            .max(weight_threshold.saturating_sub(weight)) as usize;
+        if num_committees == 0 {
+            return num_members as u32;
+        }
+        let mut committee_thresholds: Vec<u32> = self
+            .committees
+            .values()
+            .map(|committee| committee.threshold)
+            .collect();
+        committee_thresholds.sort_unstable();
+        if committee_thresholds.len() < num_committees {
+            return 0;
+        }
+        num_members as u32 + committee_thresholds[..num_committees].iter().sum::<u32>()
+    }
+
+    pub fn get_num_confirmations(&self) -> u32 {
+        self.num_confirmations
+    }
+
+    pub fn get_require_independent_confirmation(&self) -> bool {
+        self.require_independent_confirmation
+    }
+
+    pub fn get_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn get_quorum_policy(&self) -> QuorumPolicy {
+        self.quorum_policy
+    }
+
+    pub fn get_weight_threshold(&self) -> u32 {
+        self.weight_threshold
+    }
+
+    pub fn get_membership_locked_until(&self) -> U64 {
+        U64(self.membership_locked_until)
+    }
+
+    pub fn get_failure_pause_threshold(&self) -> u32 {
+        self.failure_pause_threshold
+    }
+
+    pub fn get_consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn get_active_requests_limit(&self) -> u32 {
+        self.active_requests_limit
+    }
+
+    pub fn get_max_total_requests(&self) -> Option<u32> {
+        self.max_total_requests
+    }
+
+    pub fn get_confirmation_percentage(&self) -> Option<u8> {
+        self.confirmation_percentage
+    }
+
+    /// Returns number of confirmations requests currently need,
+    /// computed from the members if `confirmation_percentage` is set.
+    pub fn get_required_confirmations(&self) -> u32 {
+        self.required_confirmations()
+    }
+
+    pub fn get_request_nonce(&self) -> RequestId {
+        self.request_nonce
+    }
+
+    pub fn get_request_nonce_floor(&self) -> RequestId {
+        self.request_nonce_floor
+    }
+
+    pub fn get_default_call_gas(&self) -> U64 {
+        U64(self.default_call_gas.0)
+    }
+
+    pub fn get_self_call_denylist(&self) -> Vec<String> {
+        self.self_call_denylist.clone()
+    }
+
+    pub fn get_max_function_call_deposit(&self) -> Option<U128> {
+        self.max_function_call_deposit.map(U128)
+    }
+
+    pub fn get_max_function_call_gas(&self) -> Option<U64> {
+        self.max_function_call_gas.map(|gas| U64(gas.0))
+    }
+
+    pub fn get_reject_replays(&self) -> bool {
+        self.reject_replays
+    }
+
+    pub fn get_replay_window(&self) -> U64 {
+        U64(self.replay_window)
+    }
+
+    pub fn get_auto_prune(&self) -> bool {
+        self.auto_prune
+    }
+
+    pub fn get_prune_after(&self) -> U64 {
+        U64(self.prune_after)
+    }
+
+    pub fn get_strict_active_requests_limit(&self) -> bool {
+        self.strict_active_requests_limit
+    }
+
+    pub fn get_collect_early_confirmations(&self) -> bool {
+        self.collect_early_confirmations
+    }
+
+    pub fn get_require_memo_above(&self) -> Option<U128> {
+        self.require_memo_above.map(U128)
+    }
+
+    pub fn get_decommission_beneficiary(&self) -> Option<AccountId> {
+        self.decommission_beneficiary.clone()
+    }
+
+    pub fn get_max_contract_code_size(&self) -> U64 {
+        U64(self.max_contract_code_size)
+    }
+
+    pub fn get_min_request_interval(&self) -> U64 {
+        U64(self.min_request_interval)
+    }
+
+    /// Returns cooldowns in nanoseconds between requests containing given action kinds.
+    pub fn get_action_kind_cooldowns(&self) -> Vec<(String, U64)> {
+        self.action_kind_cooldowns
+            .iter()
+            .map(|(kind, cooldown)| (kind, U64(cooldown)))
+            .collect()
+    }
+
+    /// Returns ids among `limit` request ids from `from_index` that are present in `confirmations`
+    /// but not in `requests`, or in `requests` but not in `confirmations`. Only requests needing
+    /// more than one confirmation have a `confirmations` entry. An empty list for all pages means
+    /// the two maps are in sync.
+    pub fn check_invariants(&self, from_index: RequestId, limit: u64) -> Vec<RequestId> {
+        let mut request_ids = self.orphaned_confirmations(from_index, limit);
+        request_ids.extend(self.requests_without_confirmations(from_index, limit));
+        request_ids.sort_unstable();
+        request_ids
+    }
+
+    /// Returns problems `add_request` would reject the request of given member with, as `"<code>: <message>"`.
+    /// An empty list means the request would be accepted.
+    pub fn validate_new_request(
+        &self,
+        request: MultiSigRequest,
+        member: MultisigMember,
+    ) -> Vec<String> {
+        if !self.members.contains(&member) {
+            return vec![format!(
+                "{}: {}",
+                MultiSigError::NotMember.code(),
+                "Predecessor must be a member or transaction signed with key of given account"
+            )];
+        }
+        self.new_request_problems(&member, &request)
+            .into_iter()
+            .map(|(error, message)| format!("{}: {}", error.code(), message))
+            .collect()
+    }
+
+    /// Returns the first reason adding the request as `as_member` or executing it would fail with,
+    /// as `"<error code>: <message>"`, or `None` if it would go through once confirmed.
+    pub fn explain_rejection(
+        &self,
+        request: MultiSigRequest,
+        as_member: MultisigMember,
+    ) -> Option<String> {
+        let execution_problem = self
+            .execution_problems(&request)
+            .into_iter()
+            .next()
+            .map(|(error, message)| format!("{}: {}", error.code(), message));
+        self.validate_new_request(request, as_member)
+            .into_iter()
+            .next()
+            .or(execution_problem)
+    }
+
+    /// Returns a human readable description per action of the request.
+    /// Actions that would be rejected on execution because of the receiver or bundling are marked.
+    pub fn describe_request(&self, request_id: RequestId) -> Vec<String> {
+        let request = self.get_request(request_id);
+        let num_actions = request.actions.len();
+        let is_self_request = request.receiver_id == env::current_account_id();
+        request
+            .actions
+            .iter()
+            .map(|action| {
+                let mut description = action.describe(&request.receiver_id);
+                if action.is_self_only() && !is_self_request {
+                    description.push_str(&format!(
+                        " [rejected: receiver must be {}]",
+                        env::current_account_id()
+                    ));
+                }
+                if action.is_single_action() && num_actions > 1 {
+                    description.push_str(" [rejected: must be a separate request]");
+                }
+                description
+            })
+            .collect()
+    }
+
+    /// Returns the number of bytes the stored request and its empty confirmation set will take.
+    /// Uses the largest current member as the originator, so it's an upper bound for any member.
+    /// Doesn't account for collection keys and indices.
+    pub fn estimate_request_storage(&self, request: MultiSigRequest) -> U64 {
+        let member = self
+            .members
+            .iter()
+            .max_by_key(|member| member.try_to_vec().map(|bytes| bytes.len()).unwrap_or(0))
+            .unwrap_or_else(|| panic_error(MultiSigError::Internal, "Multisig has no members"));
+        let request_added = MultiSigRequestWithSigner {
+            member,
+            added_timestamp: env::block_timestamp(),
+            request,
+            expires_at: None,
+            voting_members_hash: self.voting_members_hash(),
+        };
+        let confirmations: HashMap<String, u64> = HashMap::new();
+        let size = request_added
+            .try_to_vec()
+            .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize"))
+            .len()
+            + confirmations
+                .try_to_vec()
+                .unwrap_or_else(|_| panic_error(MultiSigError::Internal, "Failed to serialize"))
+                .len();
+        U64(size as u64)
+    }
+
+    /// Returns an approximate amount of gas the confirmation executing the request needs:
+    /// a base cost plus a fixed cost per action, or the gas attached to the calls it makes.
+    pub fn estimate_execution_gas(&self, request_id: RequestId) -> U64 {
+        let request_with_signer = self
+            .requests
+            .get(&request_id)
+            .unwrap_or_else(|| panic_error(MultiSigError::NoRequest, "No such request"));
+        let actions_gas: u64 = request_with_signer
+            .request
+            .actions
+            .iter()
+            .map(|action| match action {
+                MultiSigRequestAction::FunctionCall { gas, .. } => gas.0,
+                MultiSigRequestAction::NftTransfer { .. }
+                | MultiSigRequestAction::FtTransfer { .. }
+                | MultiSigRequestAction::ProxyRequest { .. } => self.default_call_gas.0,
+                MultiSigRequestAction::MultiTransfer { transfers } => {
+                    ACTION_GAS.0.saturating_mul(transfers.len() as u64)
+                }
+                _ => ACTION_GAS.0,
+            })
+            .fold(0, u64::saturating_add);
+        U64(EXECUTION_BASE_GAS
+            .0
+            .saturating_add(ON_EXECUTE_GAS.0)
+            .saturating_add(actions_gas))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::{Debug, Error, Formatter};
+
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+    use near_sdk::Balance;
+    use near_sdk::{testing_env, PublicKey};
+    use near_sdk::{AccountId, VMContext};
+
+    use super::*;
+
+    /// Used for asserts_eq.
+    /// TODO: replace with derive when https://github.com/near/near-sdk-rs/issues/165
+    impl Debug for MultiSigRequest {
+        fn fmt(&self, _f: &mut Formatter<'_>) -> Result<(), Error> {
+            panic!("Should not trigger");
+        }
+    }
+
+    pub fn alice() -> AccountId {
+        AccountId::new_unchecked("alice".to_string())
+    }
+    pub fn bob() -> AccountId {
+        AccountId::new_unchecked("bob".to_string())
+    }
+
+    const TEST_KEY: [u8; 33] = [
+        0, 247, 230, 176, 93, 224, 175, 33, 211, 72, 124, 12, 163, 219, 7, 137, 3, 37, 162, 199,
+        181, 38, 90, 244, 111, 207, 37, 216, 79, 84, 50, 83, 164,
+    ];
+
+    fn members() -> Vec<MultisigMember> {
+        vec![
+            MultisigMember::Account {
+                account_id: alice(),
+            },
+            MultisigMember::Account { account_id: bob() },
+            MultisigMember::AccessKey {
+                public_key: PublicKey::from(
+                    "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                        .parse()
+                        .unwrap(),
+                ),
+            },
+            MultisigMember::AccessKey {
+                public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            },
+        ]
+    }
+
+    fn context_with_key(key: PublicKey, amount: Balance) -> VMContext {
+        context_with_account_key(alice(), key, amount)
+    }
+
+    fn context_with_account(account_id: AccountId, amount: Balance) -> VMContext {
+        context_with_account_key(
+            account_id,
+            PublicKey::try_from(vec![
+                0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+                24, 25, 26, 27, 28, 29, 30, 31, 32, 33,
+            ])
+            .unwrap(),
+            amount,
+        )
+    }
+
+    fn context_with_account_key(
+        account_id: AccountId,
+        key: PublicKey,
+        amount: Balance,
+    ) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(account_id.clone())
+            .signer_account_id(account_id.clone())
+            .signer_account_pk(key)
+            .account_balance(amount)
+            .build()
+    }
+
+    fn context_with_key_future(key: PublicKey, amount: Balance) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(alice())
+            .block_timestamp(REQUEST_COOLDOWN + 1)
+            .predecessor_account_id(alice())
+            .signer_account_id(alice())
+            .signer_account_pk(key)
+            .account_balance(amount)
+            .build()
+    }
+
+    #[test]
+    fn test_multi_3_of_n() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                    .parse()
+                    .unwrap()
+            ),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let request_id = c.add_request(request.clone());
+        assert_eq!(c.get_request(request_id), request);
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
+                    .parse()
+                    .unwrap()
+            ),
+            amount
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 2);
+        assert_eq!(c.get_confirmations(request_id).len(), 2);
+        testing_env!(context_with_account(bob(), amount));
+        c.confirm(request_id);
+        // TODO: confirm that funds were transferred out via promise.
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_multi_add_request_and_confirm() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                    .parse()
+                    .unwrap()
+            ),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let request_id = c.add_request_and_confirm(request.clone());
+        assert_eq!(c.get_request(request_id), request);
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        // c.confirm(request_id);
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
+                    .parse()
+                    .unwrap()
+            ),
+            amount
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 2);
+        assert_eq!(c.get_confirmations(request_id).len(), 2);
+        testing_env!(context_with_account(bob(), amount));
+        c.confirm(request_id);
+        // TODO: confirm that funds were transferred out via promise.
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn add_key_delete_key_storage_cleared() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                    .parse()
+                    .unwrap()
+            ),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let new_key: PublicKey = PublicKey::from(
+            "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
+                .parse()
+                .unwrap(),
+        );
+        // vm current_account_id is alice, receiver_id must be alice
+        let request = MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddKey {
+                public_key: new_key.clone(),
+                permission: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // make request
+        c.add_request_and_confirm(request.clone());
+        // should be empty now
+        assert_eq!(c.requests.len(), 0);
+        // switch accounts
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
+                    .parse()
+                    .unwrap()
+            ),
+            amount
+        ));
+        let request2 = MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // make request but don't confirm
+        c.add_request(request2.clone());
+        // should have 1 request now
+        let new_member = MultisigMember::AccessKey {
+            public_key: new_key.clone(),
+        };
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.get_num_requests_per_member(new_member.clone()), 1);
+        // self delete key
+        let request3 = MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteMember {
+                member: new_member.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // make request and confirm
+        c.add_request_and_confirm(request3.clone());
+        // should be empty now
+        assert_eq!(c.requests.len(), 0);
+        assert_eq!(c.get_num_requests_per_member(new_member), 0);
+    }
+
+    #[test]
+    fn test_member_activity() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let member = members()[3].clone();
+        assert!(c.get_member_activity(member.clone()).is_empty());
+        let mut request = transfer_request();
+        request.actions.push(MultiSigRequestAction::CreateAccount);
+        c.add_request_and_confirm(request);
+        assert_eq!(
+            c.get_member_activity(member.clone()),
+            vec![
+                ("CreateAccount".to_string(), 1),
+                ("Transfer".to_string(), 1)
+            ]
+        );
+        c.add_request_and_confirm(transfer_request());
+        assert_eq!(
+            c.get_member_activity(member),
+            vec![
+                ("CreateAccount".to_string(), 1),
+                ("Transfer".to_string(), 2)
+            ]
+        );
+        assert!(c.get_member_activity(members()[1].clone()).is_empty());
+    }
+
+    #[test]
+    fn test_health() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        c.add_request(transfer_request());
+        c.add_request(transfer_request());
+        set_block_timestamp(REQUEST_COOLDOWN + 1);
+        c.add_request(transfer_request());
+        assert_eq!(
+            c.health(),
+            HealthReport {
+                num_members: 4,
+                num_confirmations: 3,
+                num_requests: 3,
+                num_deletable_requests: 2,
+                threshold_reachable: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_delete_member_by_key() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let action: MultiSigRequestAction = serde_json::from_str(
+            r#"{"type": "DeleteMemberByKey", "public_key": "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"}"#,
+        )
+        .unwrap();
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![action],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let members = member_list(&c);
+        assert_eq!(members.len(), 3);
+        assert!(!members.contains(&MultisigMember::AccessKey {
+            public_key: "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+        }));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_panics_add_key_different_account() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(Vec::from("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy")).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let new_key: PublicKey =
+            PublicKey::try_from(Vec::from("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"))
+                .unwrap()
+                .into();
+        // vm current_account_id is alice, receiver_id must be alice
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::AddKey {
+                public_key: new_key.clone(),
+                permission: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // make request
+        c.add_request_and_confirm(request);
+    }
+
+    #[test]
+    fn test_change_num_confirmations() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetNumConfirmations {
+                num_confirmations: 2,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.confirm(request_id);
+        assert_eq!(c.num_confirmations, 2);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Number of confirmations must be between 1 and the number of members"
+    )]
+    fn test_change_num_confirmations_above_members() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetNumConfirmations {
+                num_confirmations: 5,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Number of confirmations must be between 1 and the number of members"
+    )]
+    fn test_change_num_confirmations_to_zero() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetNumConfirmations {
+                num_confirmations: 0,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "E_ALREADY_CONFIRMED: Already confirmed this request with this key")]
+    fn test_panics_on_second_confirm() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 0);
+        c.confirm(request_id);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
+        c.confirm(request_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_COOLDOWN: Request cannot be deleted immediately after creation.")]
+    fn test_panics_delete_request() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), amount));
+        c.delete_request(request_id);
+    }
+
+    #[test]
+    fn test_delete_request_by_originator() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.delete_request(request_id);
+        assert_eq!(c.requests.len(), 0);
+        assert_eq!(c.get_num_requests_per_member(members()[3].clone()), 0);
+    }
+
+    #[test]
+    fn test_delete_request_future() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.confirm(request_id);
+        testing_env!(context_with_key_future(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        c.delete_request(request_id);
+        assert_eq!(c.requests.len(), 0);
+        assert!(c.confirmations.get(&request_id).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_delete_request_panic_wrong_key() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        // the originator itself could delete it right away
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            amount
+        ));
+        c.delete_request(request_id);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_NOT_MEMBER: Caller (predecessor or signer) is not a member of this multisig"
+    )]
+    fn test_delete_request_non_member() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(transfer_request());
+        testing_env!(context_with_key_future(
+            PublicKey::try_from(vec![0; 33]).unwrap(),
+            amount
+        ));
+        c.delete_request(request_id);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_many_requests() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        for _i in 0..16 {
+            c.add_request(MultiSigRequest {
+                receiver_id: bob(),
+                actions: vec![MultiSigRequestAction::Transfer {
+                    amount: amount.into(),
+                    memo: None,
+                }],
+                tag: None,
+                not_before: None,
+                min_confirmations_override: None,
+            });
+        }
+    }
+
+    #[test]
+    fn test_confirm_as_expected_member() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.confirm_as(
+            request_id,
+            MultisigMember::AccessKey {
+                public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            },
+        );
+        assert_eq!(c.get_confirmations(request_id).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Caller does not resolve to the expected member")]
+    fn test_confirm_as_unexpected_member() {
+        let amount = 1_000;
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            amount
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: amount.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        // predecessor is the contract itself, so the caller resolves to the signing key
+        c.confirm_as(
+            request_id,
+            MultisigMember::Account {
+                account_id: alice(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_nft_transfer_lowering() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let nft = AccountId::new_unchecked("nft".to_string());
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: nft.clone(),
+            actions: vec![MultiSigRequestAction::NftTransfer {
+                contract_id: nft.clone(),
+                token_id: "token-1".to_string(),
+                receiver_id: bob(),
+                approval_id: Some(3.into()),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.requests.len(), 0);
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == nft)
+            .unwrap();
+        match &receipt.actions[0] {
+            VmAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                gas,
+            } => {
+                assert_eq!(method_name, "nft_transfer");
+                assert_eq!(*deposit, ONE_YOCTO);
+                assert_eq!(*gas, DEFAULT_CALL_GAS);
+                let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_id"], "bob");
+                assert_eq!(args["token_id"], "token-1");
+                assert_eq!(args["approval_id"], 3);
+            }
+            _ => panic!("Expected nft_transfer function call"),
+        }
+    }
+
+    #[test]
+    fn test_proxy_request_lowering() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let child = AccountId::new_unchecked("child-multisig".to_string());
+        let inner = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: U128(5),
+                memo: Some("rent".to_string()),
+            }],
+            tag: Some("ops".to_string()),
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: child.clone(),
+            actions: vec![MultiSigRequestAction::ProxyRequest {
+                multisig_account: child.clone(),
+                request: ProxiedRequest(inner.clone()),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.requests.len(), 0);
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == child)
+            .unwrap();
+        match &receipt.actions[0] {
+            VmAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                gas,
+            } => {
+                assert_eq!(method_name, "add_request");
+                assert_eq!(*deposit, 0);
+                assert_eq!(*gas, DEFAULT_CALL_GAS);
+                let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                assert_eq!(
+                    args,
+                    json!({
+                        "request": {
+                            "receiver_id": "bob",
+                            "actions": [{ "type": "Transfer", "amount": "5", "memo": "rent" }],
+                            "tag": "ops",
+                        }
+                    })
+                );
+                let decoded: MultiSigRequest =
+                    serde_json::from_value(args["request"].clone()).unwrap();
+                assert!(decoded == inner);
+            }
+            _ => panic!("Expected add_request function call"),
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "ProxyRequest multisig_account must be equal to the request receiver_id"
+    )]
+    fn test_proxy_request_wrong_receiver() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::ProxyRequest {
+                multisig_account: AccountId::new_unchecked("child-multisig".to_string()),
+                request: ProxiedRequest(transfer_request()),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "NftTransfer contract_id must be equal to the request receiver_id")]
+    fn test_nft_transfer_wrong_contract() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::NftTransfer {
+                contract_id: AccountId::new_unchecked("nft".to_string()),
+                token_id: "token-1".to_string(),
+                receiver_id: bob(),
+                approval_id: None,
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    fn test_ft_transfer_queued_and_confirmed() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let token = AccountId::new_unchecked("token".to_string());
+        let request = MultiSigRequest {
+            receiver_id: token.clone(),
+            actions: vec![MultiSigRequestAction::FtTransfer {
+                token_id: token.clone(),
+                receiver_id: bob(),
+                amount: 500.into(),
+                memo: Some("payroll".to_string()),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let request_id = c.add_request_and_confirm(request.clone());
+        assert_eq!(c.get_request(request_id), request);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 0);
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == token)
+            .unwrap();
+        match &receipt.actions[0] {
+            VmAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                gas,
+            } => {
+                assert_eq!(method_name, "ft_transfer");
+                assert_eq!(*deposit, ONE_YOCTO);
+                assert_eq!(*gas, DEFAULT_CALL_GAS);
+                let args: serde_json::Value = serde_json::from_slice(args).unwrap();
+                assert_eq!(args["receiver_id"], "bob");
+                assert_eq!(args["amount"], "500");
+                assert_eq!(args["memo"], "payroll");
+            }
+            _ => panic!("Expected ft_transfer function call"),
+        }
+    }
+
+    #[test]
+    fn test_estimate_request_storage() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let transfer = MultiSigRequestAction::Transfer {
+            amount: 1_000.into(),
+            memo: None,
+        };
+        let small = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![transfer.clone()],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let large = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![transfer.clone(), transfer.clone(), transfer],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // first request also creates the member's request counter
+        c.add_request(small.clone());
+        let mut storage_used = |request: MultiSigRequest| {
+            let storage_before = env::storage_usage();
+            c.add_request(request);
+            env::storage_usage() - storage_before
+        };
+        let small_used = storage_used(small.clone());
+        let large_used = storage_used(large.clone());
+        let small_estimate = c.estimate_request_storage(small).0;
+        let large_estimate = c.estimate_request_storage(large).0;
+        assert!(small_estimate > 0);
+        assert!(small_estimate <= small_used);
+        // the rest is a fixed overhead of collection keys and indices
+        assert_eq!(large_used - small_used, large_estimate - small_estimate);
+    }
+
+    #[test]
+    fn test_duplicates_allowed_by_default() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        c.add_request(request.clone());
+        c.add_request(request);
+        assert_eq!(c.requests.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate request exists")]
+    fn test_reject_duplicates() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRejectDuplicates {
+                reject_duplicates: true,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.reject_duplicates);
+        c.num_confirmations = 2;
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        c.add_request(request.clone());
+        c.add_request(request);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_DUPLICATE_REQUEST: Duplicate request exists")]
+    fn test_reject_duplicates_ignores_memo() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRejectDuplicates {
+                reject_duplicates: true,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.num_confirmations = 2;
+        c.add_request(transfer_request());
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: Some("invoice 2".to_string()),
+            }],
+            tag: Some("payroll".to_string()),
+            not_before: Some(U64(1_000)),
+            min_confirmations_override: Some(3),
+        });
+    }
+
+    fn transfer_request() -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    /// Contract executing requests with a single confirmation, rejecting replays for 100 ns.
+    fn reject_replays_setup() -> MultiSigContract {
+        set_block_timestamp(1_000);
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRejectReplays {
+                reject_replays: true,
+                replay_window: U64(100),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.get_reject_replays());
+        assert_eq!(c.get_replay_window(), U64(100));
+        c.add_request_and_confirm(transfer_request());
+        c
+    }
+
+    #[test]
+    #[should_panic(expected = "E_DUPLICATE_REQUEST: Identical request was executed recently")]
+    fn test_replayed_request_rejected() {
+        let mut c = reject_replays_setup();
+        set_block_timestamp(1_099);
+        c.add_request(transfer_request());
+    }
+
+    #[test]
+    fn test_reject_replays_after_window() {
+        let mut c = reject_replays_setup();
+        // other requests are fine right away
+        c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            ..transfer_request()
+        });
+        set_block_timestamp(1_100);
+        c.add_request(transfer_request());
+        assert_eq!(c.requests.len(), 2);
+    }
+
+    fn bob2() -> AccountId {
+        AccountId::new_unchecked("bob2".to_string())
+    }
+
+    #[test]
+    fn test_migrate_my_account() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut c = MultiSigContract::new(members(), 2);
+        let queued = c.add_request_and_confirm(transfer_request());
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let approval = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::ApproveAccountMigration {
+                account_id: bob(),
+                new_account_id: bob2(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(approval);
+        c.migrate_my_account(bob2());
+        let old_member = MultisigMember::Account { account_id: bob() };
+        let new_member = MultisigMember::Account { account_id: bob2() };
+        assert!(!member_list(&c).contains(&old_member));
+        assert!(member_list(&c).contains(&new_member));
+        assert_eq!(c.list_request_ids(), vec![queued]);
+        assert_eq!(c.requests.get(&queued).unwrap().member, new_member);
+        assert!(c.has_confirmed(queued, new_member.clone()));
+        assert_eq!(c.get_num_requests_per_member(old_member), 0);
+        assert_eq!(c.get_num_requests_per_member(new_member), 1);
+        // the migrated confirmation still counts towards the threshold
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.confirm(queued);
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_migrate_my_account_history() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut c = MultiSigContract::new(members(), 2);
+        let queued = c.add_request(transfer_request());
+        c.confirm_with_note(queued, "checked".to_string());
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let approval = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::ApproveAccountMigration {
+                account_id: bob(),
+                new_account_id: bob2(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(approval);
+        c.migrate_my_account(bob2());
+        let old_member = MultisigMember::Account { account_id: bob() };
+        let new_member = MultisigMember::Account { account_id: bob2() };
+        assert_eq!(
+            c.get_request_notes(queued),
+            vec![(new_member.to_string(), "checked".to_string())]
+        );
+        assert!(c.get_member_activity(old_member).is_empty());
+        assert_eq!(
+            c.get_member_activity(new_member),
+            vec![
+                ("ApproveAccountMigration".to_string(), 1),
+                ("Transfer".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Migration to this account was not approved")]
+    fn test_migrate_my_account_not_approved() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.migrate_my_account(bob2());
+    }
+
+    fn set_member_receivers(
+        c: &mut MultiSigContract,
+        member: MultisigMember,
+        receivers: Vec<AccountId>,
+    ) {
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetMemberReceivers { member, receivers }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    fn test_member_receivers() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let bob_member = MultisigMember::Account { account_id: bob() };
+        let carol = AccountId::new_unchecked("carol".to_string());
+        set_member_receivers(&mut c, bob_member.clone(), vec![carol.clone(), bob()]);
+        assert_eq!(
+            c.get_member_receivers(bob_member.clone()),
+            vec![bob(), carol.clone()]
+        );
+        c.num_confirmations = 2;
+        testing_env!(context_with_account(bob(), 1_000));
+        c.add_request(MultiSigRequest {
+            receiver_id: carol,
+            ..transfer_request()
+        });
+        // other members aren't restricted
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.add_request(MultiSigRequest {
+            receiver_id: AccountId::new_unchecked("dave".to_string()),
+            ..transfer_request()
+        });
+        assert_eq!(c.requests.len(), 2);
+        c.num_confirmations = 1;
+        set_member_receivers(&mut c, bob_member.clone(), vec![]);
+        assert!(c.get_member_receivers(bob_member).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_RECEIVER_MISMATCH: Receiver is not allowed for this member")]
+    fn test_member_receivers_disallowed() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        set_member_receivers(
+            &mut c,
+            MultisigMember::Account { account_id: bob() },
+            vec![AccountId::new_unchecked("carol".to_string())],
+        );
+        testing_env!(context_with_account(bob(), 1_000));
+        c.add_request(MultiSigRequest {
+            receiver_id: AccountId::new_unchecked("dave".to_string()),
+            ..transfer_request()
+        });
+    }
+
+    /// Contract requiring 2 confirmations and combined weight 100, where the test key weighs 100.
+    fn weighted_quorum_setup() -> (MultiSigContract, RequestId) {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        for action in [
+            MultiSigRequestAction::SetMemberWeight {
+                member: MultisigMember::AccessKey {
+                    public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+                },
+                weight: 100,
+            },
+            MultiSigRequestAction::SetQuorumPolicy {
+                quorum_policy: QuorumPolicy::CountAndWeight,
+                weight_threshold: 100,
+            },
+        ] {
+            c.add_request_and_confirm(MultiSigRequest {
+                receiver_id: alice(),
+                actions: vec![action],
+                tag: None,
+                not_before: None,
+                min_confirmations_override: None,
+            });
+        }
+        c.num_confirmations = 2;
+        let request_id = c.add_request(transfer_request());
+        (c, request_id)
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Weight threshold must be between 1 and the combined weight of voting members"
+    )]
+    fn test_weighted_quorum_delete_member_below_threshold() {
+        let (mut c, _) = weighted_quorum_setup();
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteMember {
+                member: MultisigMember::AccessKey {
+                    public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+                },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+    }
+
+    #[test]
+    fn test_weighted_quorum_min_confirming_sets_size() {
+        let (mut c, _) = weighted_quorum_setup();
+        assert_eq!(c.min_confirming_sets_size(), 2);
+        c.num_confirmations = 1;
+        assert_eq!(c.min_confirming_sets_size(), 1);
+        // the heaviest member alone no longer has enough weight
+        c.weight_threshold = 102;
+        assert_eq!(c.min_confirming_sets_size(), 3);
+        assert!(c.health().threshold_reachable);
+        c.weight_threshold = 104;
+        assert_eq!(c.min_confirming_sets_size(), 0);
+        assert!(!c.health().threshold_reachable);
+    }
+
+    #[test]
+    fn test_weighted_quorum_weight_without_count() {
+        let (mut c, request_id) = weighted_quorum_setup();
+        assert_eq!(c.get_quorum_policy(), QuorumPolicy::CountAndWeight);
+        assert_eq!(c.get_weight_threshold(), 100);
+        c.confirm(request_id);
+        // weight is met by the single confirmation, but the count isn't
+        assert!(c.requests.get(&request_id).is_some());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert!(c.requests.get(&request_id).is_none());
+    }
+
+    #[test]
+    fn test_weighted_quorum_count_without_weight() {
+        let (mut c, request_id) = weighted_quorum_setup();
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        // the count is met, but the weight isn't
+        assert!(c.requests.get(&request_id).is_some());
+        assert_eq!(c.get_confirmations(request_id).len(), 2);
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert!(c.requests.get(&request_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Weight threshold must be between 1 and the combined weight of voting members"
+    )]
+    fn test_weighted_quorum_unreachable() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetQuorumPolicy {
+                quorum_policy: QuorumPolicy::CountAndWeight,
+                weight_threshold: members().len() as u32 + 1,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    fn lock_membership(c: &mut MultiSigContract, until: u64) {
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::LockMembership { until: U64(until) }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    fn test_lock_membership() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        lock_membership(&mut c, 1_000);
+        assert_eq!(c.get_membership_locked_until(), U64(1_000));
+        // transfers still work
+        c.add_request_and_confirm(transfer_request());
+        assert_eq!(c.requests.len(), 0);
+        set_block_timestamp(1_000);
+        c.add_request_and_confirm(add_carol_request());
+        assert_eq!(c.get_members().len(), members().len() + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_MEMBERSHIP_LOCKED: Membership locked")]
+    fn test_lock_membership_blocks_add_member() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        lock_membership(&mut c, 1_000);
+        set_block_timestamp(999);
+        c.add_request_and_confirm(add_carol_request());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_MEMBERSHIP_LOCKED: Membership locked")]
+    fn test_lock_membership_blocks_member_weight() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        lock_membership(&mut c, 1_000);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetMemberWeight {
+                member: members()[0].clone(),
+                weight: 2,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "E_MEMBERSHIP_LOCKED: Membership locked")]
+    fn test_lock_membership_blocks_migrate_my_account() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::ApproveAccountMigration {
+                account_id: bob(),
+                new_account_id: bob2(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        lock_membership(&mut c, 1_000);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.migrate_my_account(bob2());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Membership lock can only be extended")]
+    fn test_lock_membership_shortened() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        lock_membership(&mut c, 1_000);
+        lock_membership(&mut c, 0);
+    }
+
+    #[test]
+    fn test_get_members_added_after() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        let carol = MultisigMember::Account {
+            account_id: AccountId::new_unchecked("carol".to_string()),
+        };
+        let dave = MultisigMember::Account {
+            account_id: AccountId::new_unchecked("dave".to_string()),
+        };
+        for (timestamp, member) in [(200, carol.clone()), (300, dave.clone())] {
+            set_block_timestamp(timestamp);
+            c.add_request_and_confirm(MultiSigRequest {
+                receiver_id: alice(),
+                actions: vec![MultiSigRequestAction::AddMember { member }],
+                tag: None,
+                not_before: None,
+                min_confirmations_override: None,
+            });
+        }
+        assert_eq!(
+            c.get_members_added_after(U64(99)).len(),
+            members().len() + 2
+        );
+        assert_eq!(
+            c.get_members_added_after(U64(100)),
+            vec![carol, dave.clone()]
+        );
+        assert_eq!(c.get_members_added_after(U64(200)), vec![dave]);
+        assert!(c.get_members_added_after(U64(300)).is_empty());
+    }
+
+    #[test]
+    fn test_get_members_detailed() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        let observer = MultisigMember::Account {
+            account_id: AccountId::new_unchecked("carol".to_string()),
+        };
+        set_block_timestamp(200);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddObserver {
+                member: observer.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.add_request(transfer_request());
+        let detailed = c.get_members_detailed();
+        assert_eq!(detailed.len(), 5);
+        assert_eq!(
+            detailed[3],
+            MemberInfo {
+                member: members()[3].clone(),
+                weight: 1,
+                observer: false,
+                added_timestamp: U64(100),
+                num_requests: 1,
+            }
+        );
+        // observers aren't added as members, so they have no added timestamp
+        assert_eq!(
+            detailed[4],
+            MemberInfo {
+                member: observer,
+                weight: 1,
+                observer: true,
+                added_timestamp: U64(0),
+                num_requests: 0,
+            }
+        );
+    }
+
+    fn tagged_transfer_request(tag: Option<&str>) -> MultiSigRequest {
+        MultiSigRequest {
+            tag: tag.map(|tag| tag.to_string()),
+            ..transfer_request()
+        }
+    }
+
+    #[test]
+    fn test_get_requests_by_tag() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let payroll = c.add_request(tagged_transfer_request(Some("payroll")));
+        c.add_request(tagged_transfer_request(Some("grants")));
+        c.add_request(tagged_transfer_request(None));
+        let payroll_2 = c.add_request(tagged_transfer_request(Some("payroll")));
+        assert_eq!(
+            c.get_requests_by_tag("payroll".to_string(), 0, 10),
+            vec![payroll, payroll_2]
+        );
+        assert_eq!(
+            c.get_requests_by_tag("payroll".to_string(), 1, 10),
+            vec![payroll_2]
+        );
+        assert!(c.get_requests_by_tag("ops".to_string(), 0, 10).is_empty());
+        assert_eq!(c.get_request(payroll).tag, Some("payroll".to_string()));
+        // requests without a tag keep their JSON form
+        let request: MultiSigRequest = serde_json::from_value(json!({
+            "receiver_id": "bob",
+            "actions": [{"type": "Transfer", "amount": "1000"}],
+        }))
+        .unwrap();
+        assert!(request.tag.is_none());
+        assert!(serde_json::to_value(&request).unwrap().get("tag").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Tag is too long")]
+    fn test_request_tag_too_long() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let tag = "a".repeat(MAX_TAG_LEN + 1);
+        c.add_request(tagged_transfer_request(Some(&tag)));
+    }
+
+    /// Contract where the key member has 3 active requests, optionally with strict limit checks.
+    fn requests_limit_setup(strict_active_requests_limit: bool) -> MultiSigContract {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetStrictActiveRequestsLimit {
+                strict_active_requests_limit,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(
+            c.get_strict_active_requests_limit(),
+            strict_active_requests_limit
+        );
+        for _ in 0..3 {
+            c.add_request(transfer_request());
+        }
+        c
+    }
+
+    fn set_active_requests_limit_request(active_requests_limit: u32) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_active_requests_limit_below_usage_logged() {
+        let mut c = requests_limit_setup(false);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.add_request_and_confirm(set_active_requests_limit_request(2));
+        assert_eq!(c.active_requests_limit, 2);
+        assert_eq!(last_event()["event"], "active_requests_limit_below_usage");
+        assert_eq!(
+            last_event()["data"][0],
+            json!({"active_requests_limit": 2, "max_requests": 3})
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Active requests limit is below the active requests of a member"
+    )]
+    fn test_active_requests_limit_below_usage_rejected() {
+        let mut c = requests_limit_setup(true);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.add_request_and_confirm(set_active_requests_limit_request(2));
+    }
+
+    #[test]
+    fn test_get_requests() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let first = c.add_request(transfer_request());
+        let second = c.add_request(tagged_transfer_request(Some("ops")));
+        let requests = c.get_requests(vec![second, 7, first]);
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0] == Some(tagged_transfer_request(Some("ops"))));
+        assert!(requests[1].is_none());
+        assert!(requests[2] == Some(transfer_request()));
+    }
+
+    #[test]
+    fn test_get_request_counts() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        c.add_request(request.clone());
+        c.add_request(request.clone());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.add_request(request);
+        let counts = c.get_request_counts();
+        assert_eq!(counts.len(), 4);
+        let count_of = |member: &MultisigMember| {
+            counts
+                .iter()
+                .find(|(m, _)| m == member)
+                .map(|(_, count)| *count)
+                .unwrap()
+        };
+        assert_eq!(count_of(&members()[0]), 0);
+        assert_eq!(count_of(&members()[1]), 1);
+        assert_eq!(count_of(&members()[2]), 0);
+        assert_eq!(count_of(&members()[3]), 2);
+    }
+
+    fn delegate_keypair() -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    fn delegate_member() -> MultisigMember {
+        let mut key = vec![0];
+        key.extend(delegate_keypair().public.as_bytes());
+        MultisigMember::AccessKey {
+            public_key: PublicKey::try_from(key).unwrap(),
+        }
+    }
+
+    fn delegated_request_signature(c: &MultiSigContract, request: &MultiSigRequest) -> Base64VecU8 {
+        use ed25519_dalek::Signer;
+        let message = c.delegated_request_message(request);
+        delegate_keypair().sign(&message).to_bytes().to_vec().into()
+    }
+
+    #[test]
+    fn test_add_request_delegated() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut multisig_members = members();
+        multisig_members.push(delegate_member());
+        let mut c = MultiSigContract::new(multisig_members, 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // relayer isn't a member, the request is recorded under the delegating member
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("relayer".to_string()),
+            1_000
+        ));
+        let signature = delegated_request_signature(&c, &request);
+        let request_id = c.add_request_delegated(request.clone(), delegate_member(), signature);
+        assert_eq!(c.get_request(request_id), request);
+        assert_eq!(
+            c.requests.get(&request_id).unwrap().member,
+            delegate_member()
+        );
+        assert_eq!(c.get_num_requests_per_member(delegate_member()), 1);
+    }
+
+    fn confirmation_signature(c: &MultiSigContract, request_id: RequestId) -> Base64VecU8 {
+        use ed25519_dalek::Signer;
+        let message = (request_id, c.request_nonce, alice()).try_to_vec().unwrap();
+        delegate_keypair().sign(&message).to_bytes().to_vec().into()
+    }
+
+    fn delegate_public_key() -> PublicKey {
+        match delegate_member() {
+            MultisigMember::AccessKey { public_key } => public_key,
+            MultisigMember::Account { .. } => unreachable!(),
+        }
+    }
+
+    /// Contract with the delegate member and a pending request, called by a relayer.
+    fn signed_confirmation_setup() -> (MultiSigContract, RequestId) {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut multisig_members = members();
+        multisig_members.push(delegate_member());
+        let mut c = MultiSigContract::new(multisig_members, 3);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("relayer".to_string()),
+            1_000
+        ));
+        (c, request_id)
+    }
+
+    #[test]
+    fn test_confirm_signed() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        let signature = confirmation_signature(&c, request_id);
+        c.confirm_signed(request_id, delegate_public_key(), signature);
+        assert!(c.has_confirmed(request_id, delegate_member()));
+        assert_eq!(c.get_confirmations(request_id).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_SIGNATURE: Invalid signature for confirmation")]
+    fn test_confirm_signed_wrong_key() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        let signature = confirmation_signature(&c, request_id);
+        // signed by the delegate key, claimed for another member key
+        c.confirm_signed(
+            request_id,
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            signature,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_SIGNATURE: Signature was already used")]
+    fn test_confirm_signed_replayed() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        c.confirmation_ttl = 10;
+        let signature = confirmation_signature(&c, request_id);
+        c.confirm_signed(request_id, delegate_public_key(), signature.clone());
+        // once the confirmation expires, the same signature must not confirm again
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 20;
+        testing_env!(context);
+        assert!(!c.has_confirmed(request_id, delegate_member()));
+        c.confirm_signed(request_id, delegate_public_key(), signature);
+    }
+
+    fn deletion_signature(
+        c: &MultiSigContract,
+        request_id: RequestId,
+        account_id: AccountId,
+    ) -> Base64VecU8 {
+        use ed25519_dalek::Signer;
+        let message = ("delete_request", request_id, c.request_nonce, account_id)
+            .try_to_vec()
+            .unwrap();
+        delegate_keypair().sign(&message).to_bytes().to_vec().into()
+    }
+
+    /// Relayer context once the deletion cooldown has passed.
+    fn relayer_after_cooldown() {
+        let mut context =
+            context_with_account(AccountId::new_unchecked("relayer".to_string()), 1_000);
+        context.block_timestamp = REQUEST_COOLDOWN + 1;
+        testing_env!(context);
+    }
+
+    #[test]
+    fn test_delete_request_signed() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        relayer_after_cooldown();
+        let signature = deletion_signature(&c, request_id, alice());
+        c.delete_request_signed(request_id, delegate_public_key(), signature);
+        assert!(c.requests.get(&request_id).is_none());
+        assert!(c.confirmations.get(&request_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_SIGNATURE: Invalid signature for deletion")]
+    fn test_delete_request_signed_other_request() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        testing_env!(context_with_account(bob(), 1_000));
+        let other_request_id = c.add_request(transfer_request());
+        relayer_after_cooldown();
+        let signature = deletion_signature(&c, request_id, alice());
+        c.delete_request_signed(other_request_id, delegate_public_key(), signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_SIGNATURE: Invalid signature for deletion")]
+    fn test_delete_request_signed_other_contract() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        relayer_after_cooldown();
+        let signature = deletion_signature(&c, request_id, bob());
+        c.delete_request_signed(request_id, delegate_public_key(), signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_SIGNATURE: Invalid signature for deletion")]
+    fn test_delete_request_signed_with_confirmation() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        relayer_after_cooldown();
+        let signature = confirmation_signature(&c, request_id);
+        c.delete_request_signed(request_id, delegate_public_key(), signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_COOLDOWN: Request cannot be deleted immediately after creation.")]
+    fn test_delete_request_signed_before_cooldown() {
+        let (mut c, request_id) = signed_confirmation_setup();
+        let signature = deletion_signature(&c, request_id, alice());
+        c.delete_request_signed(request_id, delegate_public_key(), signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature for delegated request")]
+    fn test_add_request_delegated_forged() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut multisig_members = members();
+        multisig_members.push(delegate_member());
+        let mut c = MultiSigContract::new(multisig_members, 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let signature = delegated_request_signature(&c, &request);
+        let forged = MultiSigRequest {
+            receiver_id: alice(),
+            actions: request.actions,
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        c.add_request_delegated(forged, delegate_member(), signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature for delegated request")]
+    fn test_add_request_delegated_replayed() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut multisig_members = members();
+        multisig_members.push(delegate_member());
+        let mut c = MultiSigContract::new(multisig_members, 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let signature = delegated_request_signature(&c, &request);
+        c.add_request_delegated(request.clone(), delegate_member(), signature.clone());
+        c.add_request_delegated(request, delegate_member(), signature);
+    }
+
+    #[test]
+    fn test_get_requests_by_member() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let first = c.add_request(request.clone());
+        let second = c.add_request(request.clone());
+        testing_env!(context_with_account(bob(), 1_000));
+        let bob_request = c.add_request(request);
+        let key_member = members()[3].clone();
+        assert_eq!(
+            c.get_requests_by_member(key_member.clone(), 0, 10),
+            vec![first, second]
+        );
+        assert_eq!(
+            c.get_requests_by_member(key_member.clone(), 1, 10),
+            vec![second]
+        );
+        assert_eq!(
+            c.get_requests_by_member(members()[1].clone(), 0, 10),
+            vec![bob_request]
+        );
+        testing_env!(context_with_key_future(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.delete_request(first);
+        assert_eq!(c.get_requests_by_member(key_member, 0, 10), vec![second]);
+    }
+
+    #[test]
+    fn test_cancel_member_requests() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        for _ in 0..3 {
+            c.add_request_and_confirm(request.clone());
+        }
+        let bob_member = members()[1].clone();
+        assert_eq!(c.get_num_requests_per_member(bob_member.clone()), 3);
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let key_request = c.add_request(request);
+        let cancel_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::CancelMemberRequests {
+                member: bob_member.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_key(
+            PublicKey::from(
+                "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                    .parse()
+                    .unwrap()
+            ),
+            1_000
+        ));
+        c.confirm(cancel_id);
+        assert_eq!(c.list_request_ids(), vec![key_request]);
+        assert_eq!(c.get_num_requests_per_member(bob_member.clone()), 0);
+        assert!(member_list(&c).contains(&bob_member));
+    }
+
+    #[test]
+    fn test_add_request_idempotent() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let request_id = c.add_request_idempotent(request.clone(), "retry-1".to_string());
+        assert_eq!(
+            c.add_request_idempotent(request.clone(), "retry-1".to_string()),
+            request_id
+        );
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.get_num_requests_per_member(members()[3].clone()), 1);
+        // same nonce from another member is a different request
+        testing_env!(context_with_account(bob(), 1_000));
+        assert_ne!(
+            c.add_request_idempotent(request, "retry-1".to_string()),
+            request_id
+        );
+        assert_eq!(c.requests.len(), 2);
+    }
+
+    #[test]
+    fn test_add_request_idempotent_bounded() {
+        testing_env!(context_with_account(bob(), 1_000));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.active_requests_limit = 100;
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        for i in 0..MAX_CLIENT_NONCES_PER_MEMBER + 1 {
+            c.add_request_idempotent(request.clone(), i.to_string());
+        }
+        let client_nonces = c.client_nonces.get(&members()[1].to_string()).unwrap();
+        assert_eq!(client_nonces.len(), MAX_CLIENT_NONCES_PER_MEMBER);
+        assert_eq!(client_nonces[0].0, "1");
+    }
+
+    #[test]
+    fn test_has_confirmed() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.has_confirmed(request_id, members()[3].clone()));
+        assert!(!c.has_confirmed(request_id, members()[1].clone()));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert!(c.has_confirmed(request_id, members()[1].clone()));
+    }
+
+    #[test]
+    fn test_has_confirmed_single_confirmation() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let request_id = c.add_request(transfer_request());
+        assert!(!c.has_confirmed(request_id, members()[3].clone()));
+    }
+
+    #[test]
+    #[should_panic(expected = "No such request")]
+    fn test_has_confirmed_unknown_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 3);
+        c.has_confirmed(0, members()[0].clone());
+    }
+
+    #[test]
+    fn test_request_nonce_past_u32() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.request_nonce = u32::MAX as RequestId;
+        let last_u32_id = c.add_request(transfer_request());
+        let next_id = c.add_request(transfer_request());
+        assert_eq!(last_u32_id, u32::MAX as RequestId);
+        assert_eq!(next_id, u32::MAX as RequestId + 1);
+        c.confirm(next_id);
+        assert_eq!(c.get_confirmations(next_id).len(), 1);
+        assert!(c.get_confirmations(last_u32_id).is_empty());
+        assert_eq!(c.get_request_nonce(), u32::MAX as RequestId + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_TOO_MANY_REQUESTS: Request nonce exhausted, migrate the multisig")]
+    fn test_request_nonce_exhausted() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.request_nonce = RequestId::MAX;
+        c.add_request(transfer_request());
+    }
+
+    #[test]
+    fn test_request_nonce_floor() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRequestNonceFloor {
+                request_nonce_floor: 10,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_request_nonce_floor(), 10);
+        assert_eq!(c.get_request_nonce(), 10);
+        c.num_confirmations = 2;
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // simulate a migration that reset the nonce
+        c.request_nonce = 0;
+        assert_eq!(c.add_request(request.clone()), 10);
+        assert_eq!(c.add_request(request), 11);
+    }
+
+    #[test]
+    #[should_panic(expected = "Request nonce floor can only be increased")]
+    fn test_request_nonce_floor_cannot_decrease() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        for request_nonce_floor in [10, 5] {
+            c.add_request_and_confirm(MultiSigRequest {
+                receiver_id: alice(),
+                actions: vec![MultiSigRequestAction::SetRequestNonceFloor {
+                    request_nonce_floor,
+                }],
+                tag: None,
+                not_before: None,
+                min_confirmations_override: None,
+            });
+        }
+    }
+
+    #[test]
+    fn test_set_default_call_gas() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        assert_eq!(c.get_default_call_gas(), U64(DEFAULT_CALL_GAS.0));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetDefaultCallGas {
+                default_call_gas: U64(50_000_000_000_000),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_default_call_gas(), U64(50_000_000_000_000));
+        let token = AccountId::new_unchecked("token".to_string());
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: token.clone(),
+            actions: vec![MultiSigRequestAction::FtTransfer {
+                token_id: token.clone(),
+                receiver_id: bob(),
+                amount: 500.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == token)
+            .unwrap();
+        match &receipt.actions[0] {
+            VmAction::FunctionCall { gas, .. } => assert_eq!(*gas, Gas(50_000_000_000_000)),
+            _ => panic!("Expected ft_transfer function call"),
+        }
+    }
+
+    #[test]
+    fn test_get_members_by_kind() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 1);
+        assert_eq!(c.get_members_by_kind(true), members()[2..].to_vec());
+        assert_eq!(c.get_members_by_kind(false), members()[..2].to_vec());
+    }
+
+    fn deploy_contract_hash_request(code: &[u8]) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeployContractHash {
+                code_hash: env::sha256(code).into(),
+                code_size: U64(code.len() as u64),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_deploy_contract_hash() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let code = b"contract code".to_vec();
+        let request_id = c.add_request_and_confirm(deploy_contract_hash_request(&code));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm_with_code(request_id, code.clone().into());
+        assert_eq!(c.requests.len(), 0);
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == alice())
+            .unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::DeployContract { code }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract code hash doesn't match the committed hash")]
+    fn test_deploy_contract_hash_mismatch() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(deploy_contract_hash_request(b"contract code"));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm_with_code(request_id, b"contract c0de".to_vec().into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract code must be supplied with the final confirmation")]
+    fn test_deploy_contract_hash_without_code() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(deploy_contract_hash_request(b"contract code"));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+    }
+
+    fn function_call_request(receiver_id: AccountId, method_name: &str) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id,
+            actions: vec![MultiSigRequestAction::FunctionCall {
+                method_name: method_name.to_string(),
+                args: b"{}".to_vec().into(),
+                deposit: 0.into(),
+                gas: U64(10_000_000_000_000),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Method is not allowed to be called on this contract")]
+    fn test_self_call_denylist() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetSelfCallDenylist {
+                method_names: vec!["new".to_string()],
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_self_call_denylist(), vec!["new".to_string()]);
+        // same method on another contract is fine
+        c.add_request_and_confirm(function_call_request(bob(), "new"));
+        c.add_request_and_confirm(function_call_request(alice(), "new"));
+    }
+
+    fn set_function_call_caps(c: &mut MultiSigContract, deposit: Option<u128>, gas: Option<u64>) {
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetMaxFunctionCallDeposit {
+                max_function_call_deposit: deposit.map(U128),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetMaxFunctionCallGas {
+                max_function_call_gas: gas.map(U64),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    fn test_function_call_caps() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        assert_eq!(c.get_max_function_call_deposit(), None);
+        assert_eq!(c.get_max_function_call_gas(), None);
+        set_function_call_caps(&mut c, Some(0), Some(10_000_000_000_000));
+        assert_eq!(c.get_max_function_call_deposit(), Some(U128(0)));
+        assert_eq!(c.get_max_function_call_gas(), Some(U64(10_000_000_000_000)));
+        // exactly at the caps
+        c.add_request_and_confirm(function_call_request(bob(), "ping"));
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Function call deposit exceeds the maximum")]
+    fn test_function_call_deposit_over_cap() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        set_function_call_caps(&mut c, Some(0), None);
+        let mut request = function_call_request(bob(), "ping");
+        if let MultiSigRequestAction::FunctionCall { deposit, .. } = &mut request.actions[0] {
+            *deposit = U128(1);
+        }
+        c.add_request_and_confirm(request);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Function call gas exceeds the maximum")]
+    fn test_function_call_gas_over_cap() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        set_function_call_caps(&mut c, None, Some(5_000_000_000_000));
+        c.add_request_and_confirm(function_call_request(bob(), "ping"));
+    }
+
+    fn set_label_request(label: Option<String>) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetLabel { label }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_set_label() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        assert_eq!(c.get_label(), None);
+        c.add_request_and_confirm(set_label_request(Some("treasury".to_string())));
+        assert_eq!(c.get_label(), Some("treasury".to_string()));
+        c.add_request_and_confirm(set_label_request(None));
+        assert_eq!(c.get_label(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Label is too long")]
+    fn test_set_label_too_long() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(set_label_request(Some("a".repeat(MAX_LABEL_LEN + 1))));
+    }
+
+    #[test]
+    fn test_self_call_allowed_by_default() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(function_call_request(alice(), "new"));
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let orphaned_confirmations = c.add_request(request.clone());
+        let unconfirmed_request = c.add_request(request);
+        assert!(c.check_invariants(0, 100).is_empty());
+        c.requests.remove(&orphaned_confirmations);
+        c.confirmations.remove(&unconfirmed_request);
+        // ids that weren't handed out yet are checked too
+        let unused_id = c.request_nonce + 10;
+        c.confirmations.insert(&unused_id, &HashMap::new());
+        assert_eq!(
+            c.check_invariants(0, 100),
+            vec![orphaned_confirmations, unconfirmed_request, unused_id]
+        );
+        assert_eq!(
+            c.check_invariants(unconfirmed_request, 1),
+            vec![unconfirmed_request]
+        );
+        assert!(c.check_invariants(RequestId::MAX - 10, 100).is_empty());
+        // requests needing a single confirmation don't have an entry
+        c.num_confirmations = 1;
+        assert_eq!(
+            c.check_invariants(0, 100),
+            vec![orphaned_confirmations, unused_id]
+        );
+        assert!(c.get_confirmations(unconfirmed_request).is_empty());
+        c.num_confirmations = 2;
+        let repair = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::RepairInvariants {
+                from_index: 0,
+                limit: 100,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(repair);
+        assert!(c.check_invariants(0, 100).is_empty());
+        assert!(c.confirmations.get(&unconfirmed_request).is_some());
+    }
+
+    #[test]
+    fn test_repair_invariants_single_confirmation() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let orphaned_confirmations = c.add_request(transfer_request());
+        c.requests.remove(&orphaned_confirmations);
+        c.confirmations
+            .insert(&orphaned_confirmations, &HashMap::new());
+        let pending = c.add_request(transfer_request());
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::RepairInvariants {
+                from_index: 0,
+                limit: 100,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.check_invariants(0, 100).is_empty());
+        // the first confirmation executes the pending request, so it still needs no entry
+        assert!(c.confirmations.get(&pending).is_none());
+    }
+
+    fn membership_batch(deleted: Vec<AccountId>, added: Vec<&str>) -> MultiSigRequest {
+        let mut actions: Vec<MultiSigRequestAction> = deleted
+            .into_iter()
+            .map(|account_id| MultiSigRequestAction::DeleteMember {
+                member: MultisigMember::Account { account_id },
+            })
+            .collect();
+        actions.extend(
+            added
+                .into_iter()
+                .map(|account_id| MultiSigRequestAction::AddMember {
+                    member: MultisigMember::Account {
+                        account_id: AccountId::new_unchecked(account_id.to_string()),
+                    },
+                }),
+        );
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions,
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_membership_batch_checks_final_state() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 4);
+        // deleting an account first would drop below 4 members if checked per action
+        let request_id = c.add_request(membership_batch(
+            vec![alice(), bob()],
+            vec!["carol", "dave"],
+        ));
+        c.confirmations.insert(
+            &request_id,
+            &[
+                ("a".to_string(), 0),
+                ("b".to_string(), 0),
+                ("c".to_string(), 0),
+            ]
+            .into(),
+        );
+        c.confirm(request_id);
+        assert_eq!(c.members.len(), 4);
+        assert!(!c
+            .members
+            .contains(&MultisigMember::Account { account_id: bob() }));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Removing given member will make total number of members below number of confirmations"
+    )]
+    fn test_membership_batch_invalid_final_state() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 4);
+        let request_id = c.add_request(membership_batch(vec![alice(), bob()], vec!["carol"]));
+        c.confirmations.insert(
+            &request_id,
+            &[
+                ("a".to_string(), 0),
+                ("b".to_string(), 0),
+                ("c".to_string(), 0),
+            ]
+            .into(),
+        );
+        c.confirm(request_id);
+    }
+
+    #[test]
+    fn test_get_version() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 2);
+        assert!(!c.get_version().is_empty());
+        assert_eq!(
+            c.get_info(),
+            ContractInfo {
+                version: c.get_version(),
+                num_confirmations: 2,
+                num_members: 4,
+            }
+        );
+        assert_eq!(c.get_active_requests_limit(), ACTIVE_REQUESTS_LIMIT);
+        assert_eq!(
+            c.get_defaults(),
+            Defaults {
+                default_allowance: U128(0),
+                request_cooldown: U64(REQUEST_COOLDOWN),
+            }
+        );
+    }
+
+    fn set_min_request_interval(c: &mut MultiSigContract, min_request_interval: u64) {
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetMinRequestInterval {
+                min_request_interval: U64(min_request_interval),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Rate limited")]
+    fn test_min_request_interval_too_soon() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        set_min_request_interval(&mut c, 1_000);
+        assert_eq!(c.get_min_request_interval(), U64(1_000));
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    fn test_min_request_interval_passed() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        set_min_request_interval(&mut c, 1_000);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        c.add_request(request.clone());
+        let mut context = context_with_key(PublicKey::try_from(TEST_KEY.to_vec()).unwrap(), 1_000);
+        context.block_timestamp = 1_000;
+        testing_env!(context);
+        c.add_request(request);
+        assert_eq!(c.requests.len(), 2);
+    }
+
+    fn add_key_request(key: u8) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddKey {
+                public_key: PublicKey::try_from([vec![0], vec![key; 32]].concat()).unwrap(),
+                permission: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    fn create_account_request(funding: Option<MultiSigRequestAction>) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: "new.alice".parse().unwrap(),
+            actions: std::iter::once(MultiSigRequestAction::CreateAccount)
+                .chain(funding)
+                .collect(),
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_ARGUMENT: CreateAccount must be accompanied by a Transfer or AddKey"
+    )]
+    fn test_create_account_unfunded() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(create_account_request(None));
+    }
+
+    #[test]
+    fn test_create_account_funded() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(create_account_request(Some(
+            MultiSigRequestAction::Transfer {
+                amount: U128(100),
+                memo: None,
+            },
+        )));
+        c.add_request(create_account_request(Some(
+            MultiSigRequestAction::AddKey {
+                public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+                permission: None,
+            },
+        )));
+        assert_eq!(c.list_request_ids().len(), 2);
+    }
+
+    fn set_add_key_cooldown(c: &mut MultiSigContract) {
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetActionKindCooldown {
+                kind: "AddKey".to_string(),
+                cooldown: Some(U64(1_000)),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "E_RATE_LIMITED: Action kind is rate limited")]
+    fn test_action_kind_cooldown_too_soon() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        set_add_key_cooldown(&mut c);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(0);
+        assert_eq!(
+            c.get_action_kind_cooldowns(),
+            vec![("AddKey".to_string(), U64(1_000))]
+        );
+        c.add_request(add_key_request(1));
+        // other kinds aren't limited
+        c.add_request(transfer_request());
+        c.add_request(add_key_request(2));
+    }
+
+    #[test]
+    fn test_action_kind_cooldown_passed() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        set_add_key_cooldown(&mut c);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(0);
+        c.add_request(add_key_request(1));
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 1_000;
+        testing_env!(context);
+        c.add_request(add_key_request(2));
+        assert_eq!(c.requests.len(), 2);
+    }
+
+    #[test]
+    fn test_transfer_memo() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request: MultiSigRequest = near_sdk::serde_json::from_value(json!({
+            "receiver_id": "bob",
+            "actions": [{"type": "Transfer", "amount": "1000"}]
+        }))
+        .unwrap();
+        assert!(
+            request.actions[0]
+                == MultiSigRequestAction::Transfer {
+                    amount: 1_000.into(),
+                    memo: None,
+                }
+        );
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: Some("invoice 42".to_string()),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(
+            near_sdk::serde_json::to_value(c.get_request(request_id)).unwrap(),
+            json!({
+                "receiver_id": "bob",
+                "actions": [{"type": "Transfer", "amount": "1000", "memo": "invoice 42"}]
+            })
+        );
+    }
+
+    /// Returns the last logged event as JSON.
+    fn last_event() -> serde_json::Value {
+        let logs = near_sdk::test_utils::get_logs();
+        let event = logs.last().unwrap().strip_prefix("EVENT_JSON:").unwrap();
+        serde_json::from_str(event).unwrap()
+    }
+
+    #[test]
+    fn test_confirm_with_note() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(transfer_request());
+        c.confirm_with_note(request_id, "verified invoice #123".to_string());
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            1_000
+        ));
+        c.confirm_with_note(request_id, "amount matches".to_string());
+        let notes = c.get_request_notes(request_id);
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].1, "verified invoice #123");
+        assert_eq!(notes[1].1, "amount matches");
+        assert_ne!(notes[0].0, notes[1].0);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm_with_note(request_id, "ok".to_string());
+        assert!(c.requests.get(&request_id).is_none());
+        assert!(c.get_request_notes(request_id).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Note must be between 1 and 128 bytes")]
+    fn test_confirm_with_note_too_long() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request(transfer_request());
+        c.confirm_with_note(request_id, "a".repeat(MAX_NOTE_LEN + 1));
+    }
+
+    #[test]
+    fn test_emit_state_snapshot() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let first = c.add_request_and_confirm(transfer_request());
+        let second = c.add_request(transfer_request());
+        let third = c.add_request(transfer_request());
+        testing_env!(context_with_account(bob(), 1_000));
+        assert_eq!(c.emit_state_snapshot(1, 10), 2);
+        let events: Vec<serde_json::Value> = near_sdk::test_utils::get_logs()
+            .iter()
+            .map(|log| serde_json::from_str(log.strip_prefix("EVENT_JSON:").unwrap()).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "request_snapshot");
+        assert_eq!(events[0]["data"][0]["request_id"], second);
+        assert_eq!(events[1]["data"][0]["request_id"], third);
+        assert_eq!(events[1]["data"][0]["request"]["receiver_id"], "bob");
+        assert_eq!(events[1]["data"][0]["confirmations"], serde_json::json!([]));
+
+        testing_env!(context_with_account(bob(), 1_000));
+        assert_eq!(c.emit_state_snapshot(0, 1), 1);
+        assert_eq!(last_event()["data"][0]["request_id"], first);
+        assert_eq!(
+            last_event()["data"][0]["confirmations"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(c.requests.len(), 3);
+    }
+
+    fn context_with_promise_result(promise_result: PromiseResult) {
+        context_with_promise_results(vec![promise_result]);
+    }
+
+    fn context_with_promise_results(promise_results: Vec<PromiseResult>) {
+        testing_env!(
+            context_with_account(alice(), 1_000),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            promise_results,
+        );
+    }
+
+    #[test]
+    fn test_execute_schedules_callback() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let callback = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == alice() && !r.receipt_indices.is_empty())
+            .unwrap();
+        match &callback.actions[0] {
+            VmAction::FunctionCall { method_name, .. } => assert_eq!(method_name, "on_execute"),
+            _ => panic!("Expected on_execute callback"),
+        }
+    }
+
+    #[test]
+    fn test_on_execute_success() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        context_with_promise_result(PromiseResult::Successful(vec![]));
+        assert!(c.on_execute(3, bob()));
+        assert_eq!(
+            last_event(),
+            json!({
+                "standard": "multisig",
+                "version": "1.0.0",
+                "event": "request_executed",
+                "data": [{"request_id": 3, "receiver_id": "bob"}],
+            })
+        );
+    }
+
+    fn circuit_breaker_setup(failure_pause_threshold: u32) -> MultiSigContract {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetFailurePauseThreshold {
+                failure_pause_threshold,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let mut c = circuit_breaker_setup(3);
+        assert_eq!(c.get_failure_pause_threshold(), 3);
+        context_with_promise_result(PromiseResult::Failed);
+        c.on_execute(0, bob());
+        c.on_execute(1, bob());
+        assert_eq!(c.get_consecutive_failures(), 2);
+        // a success in between starts over
+        context_with_promise_result(PromiseResult::Successful(vec![]));
+        c.on_execute(2, bob());
+        assert_eq!(c.get_consecutive_failures(), 0);
+        context_with_promise_result(PromiseResult::Failed);
+        c.on_execute(3, bob());
+        c.on_execute(4, bob());
+        assert!(!c.get_paused());
+        c.on_execute(5, bob());
+        assert!(c.get_paused());
+        assert_eq!(c.get_consecutive_failures(), 3);
+        assert_eq!(last_event()["event"], "circuit_breaker_tripped");
+        assert_eq!(last_event()["data"][0]["consecutive_failures"], 3);
+
+        // requests to the multisig itself still go through, so it can be unpaused
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetPaused { paused: false }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(!c.get_paused());
+        assert_eq!(c.get_consecutive_failures(), 0);
+        c.add_request_and_confirm(transfer_request());
+        assert!(c.requests.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_PAUSED: Multisig is paused")]
+    fn test_paused_rejects_requests() {
+        let mut c = circuit_breaker_setup(1);
+        context_with_promise_result(PromiseResult::Failed);
+        c.on_execute(0, bob());
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.add_request(transfer_request());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_PAUSED: Multisig is paused")]
+    fn test_paused_blocks_execution() {
+        let mut c = circuit_breaker_setup(1);
+        c.num_confirmations = 2;
+        let request_id = c.add_request_and_confirm(transfer_request());
+        context_with_promise_result(PromiseResult::Failed);
+        c.on_execute(0, bob());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+    }
+
+    #[test]
+    fn test_on_execute_failure() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        context_with_promise_result(PromiseResult::Failed);
+        assert!(!c.on_execute(3, bob()));
+        assert_eq!(
+            last_event(),
+            json!({
+                "standard": "multisig",
+                "version": "1.0.0",
+                "event": "request_execution_failed",
+                "data": [{
+                    "request_id": 3,
+                    "receiver_id": "bob",
+                    "requeued": false,
+                    "first_failed_index": 0,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_on_execute_reports_first_failed_index() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        context_with_promise_results(vec![
+            PromiseResult::Successful(vec![]),
+            PromiseResult::Failed,
+            PromiseResult::Failed,
+        ]);
+        assert!(!c.on_execute(3, bob()));
+        assert_eq!(last_event()["event"], "request_execution_failed");
+        assert_eq!(last_event()["data"][0]["first_failed_index"], 1);
+    }
+
+    /// Executes a transfer to bob, optionally retrying failed executions, and fails it.
+    fn fail_transfer_execution(retry_failed_executions: bool) -> (MultiSigContract, RequestId) {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRetryFailedExecutions {
+                retry_failed_executions,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert_eq!(c.get_retry_failed_executions(), retry_failed_executions);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 0);
+        context_with_promise_result(PromiseResult::Failed);
+        assert!(!c.on_execute(request_id, bob()));
+        (c, request_id)
+    }
+
+    #[test]
+    fn test_retry_failed_executions_config_action() {
+        let (mut c, _) = fail_transfer_execution(true);
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let request_id = c.add_request_and_confirm(set_label_request(Some("treasury".to_string())));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert_eq!(c.get_label(), Some("treasury".to_string()));
+        assert!(c.executing_requests.get(&request_id).is_none());
+    }
+
+    #[test]
+    fn test_failed_execution_requeued() {
+        let (c, request_id) = fail_transfer_execution(true);
+        assert_eq!(
+            last_event()["data"][0],
+            json!({
+                "request_id": request_id,
+                "receiver_id": "bob",
+                "requeued": true,
+                "first_failed_index": 0,
+            })
+        );
+        assert_eq!(c.list_request_ids(), vec![request_id]);
+        assert!(c.get_confirmations(request_id).is_empty());
+        let bob_member = MultisigMember::Account { account_id: bob() };
+        assert_eq!(
+            c.get_requests_by_member(bob_member.clone(), 0, 10),
+            vec![request_id]
+        );
+        assert_eq!(c.get_num_requests_per_member(bob_member), 1);
+    }
+
+    #[test]
+    fn test_failed_execution_not_requeued() {
+        let (c, request_id) = fail_transfer_execution(false);
+        assert_eq!(
+            last_event()["data"][0],
+            json!({
+                "request_id": request_id,
+                "receiver_id": "bob",
+                "requeued": false,
+                "first_failed_index": 0,
+            })
+        );
+        assert!(c.list_request_ids().is_empty());
+    }
+
+    #[test]
+    fn test_get_effective_threshold() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![
+                MultiSigRequestAction::Transfer {
+                    amount: 1_000.into(),
+                    memo: None,
+                },
+                MultiSigRequestAction::DeleteMember {
+                    member: MultisigMember::Account { account_id: bob() },
+                },
+            ],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_effective_threshold(request_id), 3);
+    }
+
+    fn request_with_override(min_confirmations_override: u32) -> MultiSigRequest {
+        MultiSigRequest {
+            min_confirmations_override: Some(min_confirmations_override),
+            ..transfer_request()
+        }
+    }
+
+    #[test]
+    fn test_min_confirmations_override() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(request_with_override(3));
+        assert_eq!(c.get_effective_threshold(request_id), 3);
+        assert_eq!(c.get_all_progress(0, 10), vec![(request_id, 1, 3)]);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        // the global threshold is met, but the override needs another signature
+        assert!(c.requests.get(&request_id).is_some());
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert!(c.requests.get(&request_id).is_none());
+    }
+
+    #[test]
+    fn test_min_confirmations_override_never_lowers() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(request_with_override(1));
+        assert_eq!(c.get_effective_threshold(request_id), 2);
+        assert!(c.requests.get(&request_id).is_some());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_ARGUMENT: Confirmation override exceeds the number of members who can confirm"
+    )]
+    fn test_min_confirmations_override_unreachable() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(request_with_override(members().len() as u32 + 1));
+    }
+
+    #[test]
+    fn test_try_getters() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(transfer_request());
+        assert!(c.try_get_request(request_id) == Some(transfer_request()));
+        assert_eq!(
+            c.try_get_confirmations(request_id),
+            Some(c.get_confirmations(request_id))
+        );
+        let full = c.try_get_request_full(request_id).unwrap();
+        assert_eq!(full.request_id, request_id);
+        assert_eq!(full.confirmations.len(), 1);
+        assert!(full.request == transfer_request());
+
+        let unknown = request_id + 1;
+        assert!(c.try_get_request(unknown).is_none());
+        assert!(c.try_get_confirmations(unknown).is_none());
+        assert!(c.try_get_request_full(unknown).is_none());
+    }
+
+    #[test]
+    fn test_would_execute() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request(transfer_request());
+        assert!(!c.would_execute(request_id));
+        c.confirm(request_id);
+        assert!(!c.would_execute(request_id));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert!(c.would_execute(request_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_NO_REQUEST: No such request")]
+    fn test_get_effective_threshold_unknown_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 3);
+        c.get_effective_threshold(0);
+    }
+
+    #[test]
+    fn test_list_requests_by_age() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        // ids don't follow age, as if the nonce was reset
+        for (request_nonce, block_timestamp) in [(5, 300), (1, 100), (3, 200)] {
+            let mut context =
+                context_with_key(PublicKey::try_from(TEST_KEY.to_vec()).unwrap(), 1_000);
+            context.block_timestamp = block_timestamp;
+            testing_env!(context);
+            c.request_nonce = request_nonce;
+            c.add_request(request.clone());
+        }
+        assert_eq!(
+            c.list_requests_by_age(0, 10),
+            vec![(1, U64(100)), (3, U64(200)), (5, U64(300))]
+        );
+        assert_eq!(c.list_requests_by_age(1, 1), vec![(3, U64(200))]);
+    }
+
+    #[test]
+    fn test_get_request_added_timestamp() {
+        let mut context = context_with_key(PublicKey::try_from(TEST_KEY.to_vec()).unwrap(), 1_000);
+        context.block_timestamp = 12_345;
+        testing_env!(context);
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_request_added_timestamp(request_id), U64(12_345));
+    }
+
+    #[test]
+    fn test_seconds_until_deletable() {
+        let mut context = context_with_key(PublicKey::try_from(TEST_KEY.to_vec()).unwrap(), 1_000);
+        context.block_timestamp = 1_000;
+        testing_env!(context.clone());
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request(transfer_request());
+        assert_eq!(
+            c.get_delete_eligible_at(request_id),
+            U64(1_000 + REQUEST_COOLDOWN)
+        );
+        assert_eq!(c.seconds_until_deletable(request_id), U64(REQUEST_COOLDOWN));
+
+        context.block_timestamp = 1_000 + REQUEST_COOLDOWN + 1;
+        testing_env!(context);
+        assert_eq!(c.seconds_until_deletable(request_id), U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "No such request")]
+    fn test_seconds_until_deletable_unknown() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 2);
+        c.seconds_until_deletable(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "No such request")]
+    fn test_get_request_added_timestamp_unknown() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 2);
+        c.get_request_added_timestamp(0);
+    }
+
+    fn committee_request() -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::CreateCommittee {
+                name: "ops".to_string(),
+                members: vec![
+                    MultisigMember::Account {
+                        account_id: AccountId::new_unchecked("carol".to_string()),
+                    },
+                    MultisigMember::Account {
+                        account_id: AccountId::new_unchecked("dave".to_string()),
+                    },
+                ],
+                threshold: 2,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_committee_quorum_is_one_confirmation() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(committee_request());
+        assert_eq!(c.get_committees()[0].1.threshold, 2);
+        c.num_confirmations = 2;
+        testing_env!(context_with_account(bob(), 1_000));
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("carol".to_string()),
+            1_000
+        ));
+        c.confirm(request_id);
+        // committee threshold isn't met yet, so no main confirmation
+        assert_eq!(c.get_confirmations(request_id).len(), 1);
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("dave".to_string()),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_min_confirming_sets_size() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(committee_request());
+        c.num_confirmations = 3;
+        assert_eq!(c.min_confirming_sets_size(), 3);
+        // all 4 members and both members of the committee
+        c.num_confirmations = 5;
+        assert_eq!(c.min_confirming_sets_size(), 6);
+        c.num_confirmations = 6;
+        assert_eq!(c.min_confirming_sets_size(), 0);
+    }
+
+    #[test]
+    fn test_committee_vote_counts_once() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(committee_request());
+        c.num_confirmations = 3;
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        for account_id in ["carol", "dave"] {
+            testing_env!(context_with_account(
+                AccountId::new_unchecked(account_id.to_string()),
+                1_000
+            ));
+            c.confirm(request_id);
+        }
+        assert_eq!(c.get_confirmations(request_id).len(), 2);
+        assert!(c
+            .get_confirmations(request_id)
+            .contains(&"committee:ops".to_string()));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Committee member must not be a member of the multisig or another committee"
+    )]
+    fn test_committee_member_overlap() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(committee_request());
+        let mut request = committee_request();
+        if let MultiSigRequestAction::CreateCommittee { name, .. } = &mut request.actions[0] {
+            *name = "audit".to_string();
+        }
+        c.add_request_and_confirm(request);
+    }
+
+    #[test]
+    fn test_dissolve_committee() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(committee_request());
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DissolveCommittee {
+                name: "ops".to_string(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.get_committees().is_empty());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Number of confirmations exceeds the number of members who can confirm"
+    )]
+    fn test_confirm_unreachable_threshold() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 4);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        // force membership below the threshold
+        c.members
+            .remove(&MultisigMember::Account { account_id: bob() });
+        c.confirm(request_id);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_NOT_MEMBER: Predecessor must be a member or transaction signed with key of given account"
+    )]
+    fn test_error_code_not_member() {
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("carol".to_string()),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_NO_REQUEST: No such request: either wrong number or already confirmed"
+    )]
+    fn test_error_code_no_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.confirm(7);
+    }
+
+    #[test]
+    fn test_describe_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let key = PublicKey::try_from(TEST_KEY.to_vec()).unwrap();
+        let key_string = String::from(&key);
+        let actions = vec![
+            MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: Some("salary".to_string()),
+            },
+            MultiSigRequestAction::CreateAccount,
+            MultiSigRequestAction::DeployContract {
+                code: vec![0; 10].into(),
+            },
+            MultiSigRequestAction::DeployContractHash {
+                code_hash: vec![0xab, 0x01].into(),
+                code_size: U64(10),
+            },
+            MultiSigRequestAction::AddMember {
+                member: MultisigMember::Account { account_id: bob() },
+            },
+            MultiSigRequestAction::DeleteMember {
+                member: MultisigMember::AccessKey {
+                    public_key: key.clone(),
+                },
+            },
+            MultiSigRequestAction::AddKey {
+                public_key: key.clone(),
+                permission: None,
+            },
+            MultiSigRequestAction::AddKey {
+                public_key: key.clone(),
+                permission: Some(FunctionCallPermission {
+                    allowance: None,
+                    receiver_id: bob(),
+                    method_names: vec!["a".to_string(), "b".to_string()],
+                }),
+            },
+            MultiSigRequestAction::FunctionCall {
+                method_name: "ping".to_string(),
+                args: b"{}".to_vec().into(),
+                deposit: 5.into(),
+                gas: U64(100),
+            },
+            MultiSigRequestAction::NftTransfer {
+                contract_id: alice(),
+                token_id: "1".to_string(),
+                receiver_id: bob(),
+                approval_id: None,
+                memo: None,
+            },
+            MultiSigRequestAction::FtTransfer {
+                token_id: alice(),
+                receiver_id: bob(),
+                amount: 7.into(),
+                memo: None,
+            },
+            MultiSigRequestAction::SetNumConfirmations {
+                num_confirmations: 3,
+            },
+            MultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit: 5,
+            },
+            MultiSigRequestAction::CancelMemberRequests {
+                member: MultisigMember::Account { account_id: bob() },
+            },
+            MultiSigRequestAction::SetRejectDuplicates {
+                reject_duplicates: true,
+            },
+            MultiSigRequestAction::SetRequestNonceFloor {
+                request_nonce_floor: 9,
+            },
+            MultiSigRequestAction::SetDefaultCallGas {
+                default_call_gas: U64(42),
+            },
+            MultiSigRequestAction::SetSelfCallDenylist {
+                method_names: vec!["new".to_string()],
+            },
+            MultiSigRequestAction::RepairInvariants {
+                from_index: 0,
+                limit: 10,
+            },
+            MultiSigRequestAction::SetMinRequestInterval {
+                min_request_interval: U64(60),
+            },
+            MultiSigRequestAction::CreateCommittee {
+                name: "ops".to_string(),
+                members: vec![MultisigMember::Account { account_id: bob() }],
+                threshold: 1,
+            },
+            MultiSigRequestAction::DissolveCommittee {
+                name: "ops".to_string(),
+            },
+        ];
+        for action in &actions {
+            assert_eq!(serde_json::to_value(action).unwrap()["type"], action.kind());
+        }
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: alice(),
+            actions,
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let separate = " [rejected: must be a separate request]";
+        assert_eq!(
+            c.describe_request(request_id),
+            vec![
+                "Transfer 1000 yⓃ to alice (salary)".to_string(),
+                "Create account alice".to_string(),
+                "Deploy 10 bytes of code to alice".to_string(),
+                "Deploy 10 bytes of code with sha256 ab01 to alice".to_string(),
+                "Add member Account:bob".to_string(),
+                format!("Delete member AccessKey:{}", key_string),
+                format!("Add full access key {}", key_string),
+                format!("Add key {} for calling [a, b] on bob", key_string),
+                "Call ping on alice with 5 yⓃ deposit and 100 gas".to_string(),
+                "Transfer NFT 1 of alice to bob".to_string(),
+                "Transfer 7 of token alice to bob".to_string(),
+                format!("Set number of confirmations to 3{}", separate),
+                format!("Set active requests limit to 5{}", separate),
+                "Cancel requests of member Account:bob".to_string(),
+                format!("Set reject duplicates to true{}", separate),
+                format!("Set request nonce floor to 9{}", separate),
+                format!("Set default call gas to 42{}", separate),
+                format!("Set self call denylist to [new]{}", separate),
+                format!("Repair invariants of 10 request ids from 0{}", separate),
+                format!("Set minimum request interval to 60 ns{}", separate),
+                "Create committee ops of 1 out of [Account:bob]".to_string(),
+                "Dissolve committee ops".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_request_wrong_receiver() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![
+                MultiSigRequestAction::Transfer {
+                    amount: 1_000.into(),
+                    memo: None,
+                },
+                MultiSigRequestAction::AddMember {
+                    member: MultisigMember::Account { account_id: bob() },
+                },
+            ],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(
+            c.describe_request(request_id),
+            vec![
+                "Transfer 1000 yⓃ to bob".to_string(),
+                "Add member Account:bob [rejected: receiver must be alice]".to_string(),
+            ]
+        );
+    }
+
+    fn set_block_timestamp(block_timestamp: u64) {
+        let mut context = context_with_key(PublicKey::try_from(TEST_KEY.to_vec()).unwrap(), 1_000);
+        context.block_timestamp = block_timestamp;
+        testing_env!(context);
+    }
+
+    #[test]
+    fn test_get_ready_to_execute() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 3);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let ready = c.add_request_and_confirm(request.clone());
+        // stays below the threshold with a single confirmation
+        c.add_request_and_confirm(request);
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 200;
+        testing_env!(context);
+        c.confirm(ready);
+        assert!(c.get_ready_to_execute().is_empty());
+        // lowering the threshold leaves the request confirmed but not executed
+        c.num_confirmations = 2;
+        assert_eq!(c.get_ready_to_execute(), vec![(ready, U64(200))]);
+    }
+
+    fn scheduled_request() -> MultiSigRequest {
+        MultiSigRequest {
+            not_before: Some(U64(1_000)),
+            min_confirmations_override: None,
+            ..transfer_request()
+        }
+    }
+
+    /// Request with `not_before` of 1000 confirmed by two of the members at 100.
+    fn early_confirmed_setup() -> (MultiSigContract, RequestId) {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(scheduled_request());
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 100;
+        testing_env!(context);
+        c.confirm(request_id);
+        (c, request_id)
+    }
+
+    #[test]
+    fn test_not_before_defers_execution() {
+        let (mut c, request_id) = early_confirmed_setup();
+        assert_eq!(c.get_confirmations(request_id).len(), 2);
+        assert_eq!(c.get_ready_to_execute(), vec![(request_id, U64(1_000))]);
+        set_block_timestamp(1_000);
+        c.execute_ready(request_id, None);
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_execute_ready_with_code() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        let code = b"contract code".to_vec();
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            not_before: Some(U64(1_000)),
+            ..deploy_contract_hash_request(&code)
+        });
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 100;
+        testing_env!(context);
+        c.confirm(request_id);
+        set_block_timestamp(1_000);
+        c.execute_ready(request_id, Some(code.clone().into()));
+        assert_eq!(c.requests.len(), 0);
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == alice())
+            .unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::DeployContract { code }]);
+    }
+
+    #[test]
+    fn test_action_variant_indices() {
+        // requests stored by older versions must keep decoding to the same actions
+        let index = |action: MultiSigRequestAction| action.try_to_vec().unwrap()[0];
+        assert_eq!(
+            index(MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }),
+            0
+        );
+        assert_eq!(index(MultiSigRequestAction::CreateAccount), 1);
+        assert_eq!(
+            index(MultiSigRequestAction::SetNumConfirmations {
+                num_confirmations: 2
+            }),
+            7
+        );
+        assert_eq!(
+            index(MultiSigRequestAction::SetActiveRequestsLimit {
+                active_requests_limit: 2
+            }),
+            8
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "E_EXPIRED: Request expired")]
+    fn test_execute_ready_expired() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_with_ttl(scheduled_request(), U64(500));
+        c.confirm(request_id);
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 100;
+        testing_env!(context);
+        c.confirm(request_id);
+        set_block_timestamp(1_000);
+        c.execute_ready(request_id, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_NOT_YET_ACTIVE: Request not yet active")]
+    fn test_execute_ready_too_early() {
+        let (mut c, request_id) = early_confirmed_setup();
+        set_block_timestamp(999);
+        c.execute_ready(request_id, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_NOT_YET_ACTIVE: Request not yet active")]
+    fn test_early_confirmations_disabled() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetCollectEarlyConfirmations {
+                collect_early_confirmations: false,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(!c.get_collect_early_confirmations());
+        c.add_request_and_confirm(scheduled_request());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_CONFIG: Confirmation ttl must be at most a year")]
+    fn test_confirmation_ttl_too_long() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetConfirmationTtl {
+                confirmation_ttl: U64(MAX_CONFIRMATION_TTL + 1),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    #[test]
+    fn test_confirmation_ttl() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetConfirmationTtl {
+                confirmation_ttl: U64(100),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_confirmation_ttl(), U64(100));
+        c.num_confirmations = 2;
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        set_block_timestamp(200);
+        assert!(c.get_confirmations(request_id).is_empty());
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = 200;
+        testing_env!(context);
+        // the old confirmation expired, so this one isn't enough
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
+        // the expired member can confirm again
+        set_block_timestamp(250);
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 0);
+    }
+
+    #[test]
+    fn test_validate_new_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let member = MultisigMember::AccessKey {
+            public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+        };
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        assert!(c
+            .validate_new_request(request.clone(), member.clone())
+            .is_empty());
+        c.reject_duplicates = true;
+        for _ in 0..ACTIVE_REQUESTS_LIMIT {
+            c.requests.insert(
+                &c.request_nonce,
+                &MultiSigRequestWithSigner {
+                    request: request.clone(),
+                    member: member.clone(),
+                    added_timestamp: 0,
+                    expires_at: None,
+                    voting_members_hash: c.voting_members_hash(),
+                },
+            );
+            c.request_nonce += 1;
+        }
+        c.num_requests_pk
+            .insert(&member.to_string(), &ACTIVE_REQUESTS_LIMIT);
+        assert_eq!(
+            c.validate_new_request(request.clone(), member),
+            vec![
+                "E_TOO_MANY_REQUESTS: Account has too many active requests. Confirm or delete some.",
+                "E_DUPLICATE_REQUEST: Duplicate request exists",
+            ]
+        );
+        assert_eq!(
+            c.validate_new_request(
+                request,
+                MultisigMember::Account {
+                    account_id: AccountId::new_unchecked("carol".to_string()),
+                }
+            ),
+            vec!["E_NOT_MEMBER: Predecessor must be a member or transaction signed with key of given account"]
+        );
+    }
+
+    #[test]
+    fn test_explain_rejection() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 2);
+        let member = MultisigMember::Account { account_id: bob() };
+        assert_eq!(
+            c.explain_rejection(transfer_request(), member.clone()),
+            None
+        );
+        let set_confirmations = MultiSigRequestAction::SetNumConfirmations {
+            num_confirmations: 1,
+        };
+        assert_eq!(
+            c.explain_rejection(
+                MultiSigRequest {
+                    actions: vec![set_confirmations.clone()],
+                    ..transfer_request()
+                },
+                member.clone()
+            ),
+            Some(
+                "E_RECEIVER_MISMATCH: This method only works when receiver_id is equal to current_account_id"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            c.explain_rejection(
+                MultiSigRequest {
+                    receiver_id: alice(),
+                    actions: vec![
+                        set_confirmations,
+                        MultiSigRequestAction::Transfer {
+                            amount: U128(1),
+                            memo: None,
+                        },
+                    ],
+                    tag: None,
+                    not_before: None,
+                    min_confirmations_override: None,
+                },
+                member.clone()
+            ),
+            Some("E_SEPARATE_REQUEST: This method should be a separate request".to_string())
+        );
+        assert_eq!(
+            c.explain_rejection(
+                MultiSigRequest {
+                    actions: vec![MultiSigRequestAction::SweepTransfer {
+                        receiver_id: alice(),
+                        reserve: U128(0),
+                    }],
+                    ..transfer_request()
+                },
+                member
+            ),
+            Some(
+                "E_RECEIVER_MISMATCH: SweepTransfer receiver_id must be equal to the request receiver_id"
+                    .to_string()
+            )
+        );
+        // submission checks come first
+        assert_eq!(
+            c.explain_rejection(
+                transfer_request(),
+                MultisigMember::Account {
+                    account_id: AccountId::new_unchecked("carol".to_string()),
+                }
+            ),
+            Some(
+                "E_NOT_MEMBER: Predecessor must be a member or transaction signed with key of given account"
+                    .to_string()
+            )
+        );
+    }
+
+    fn member_list(c: &MultiSigContract) -> Vec<MultisigMember> {
+        c.get_members()
+            .into_iter()
+            .map(|member| member.member)
+            .collect()
+    }
+
+    fn add_observer(c: &mut MultiSigContract) -> MultisigMember {
+        let observer = MultisigMember::Account {
+            account_id: AccountId::new_unchecked("carol".to_string()),
+        };
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddObserver {
+                member: observer.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        observer
+    }
+
+    #[test]
+    fn test_delete_access_key_observer() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let observer_key = PublicKey::try_from(vec![0; 33]).unwrap();
+        let observer = MultisigMember::AccessKey {
+            public_key: observer_key.clone(),
+        };
+        // the observer never gets an access key, so there's none to delete
+        let no_key_changes = || {
+            get_created_receipts().iter().all(|receipt| {
+                receipt.actions.iter().all(|action| match action {
+                    VmAction::AddKeyWithFunctionCall { public_key, .. }
+                    | VmAction::DeleteKey { public_key } => public_key != &observer_key,
+                    _ => true,
+                })
+            })
+        };
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddObserver {
+                member: observer.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(no_key_changes());
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteMember {
+                member: observer.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(no_key_changes());
+        assert!(!member_list(&c).contains(&observer));
+        assert!(c.get_observers().is_empty());
+    }
+
+    #[test]
+    fn test_observer_can_view() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let observer = add_observer(&mut c);
+        assert!(c.get_members().contains(&MemberWithRole {
+            member: observer.clone(),
+            observer: true,
+        }));
+        assert!(c.get_members().contains(&MemberWithRole {
+            member: members()[0].clone(),
+            observer: false,
+        }));
+        assert_eq!(c.get_observers(), vec![observer.clone()]);
+        assert_eq!(c.num_voting_members(), 4);
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("carol".to_string()),
+            1_000
+        ));
+        assert_eq!(c.get_num_confirmations(), 1);
+        assert_eq!(c.get_num_requests_per_member(observer.clone()), 0);
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::RemoveObserver {
+                member: observer.clone(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(!member_list(&c).contains(&observer));
+        assert!(c.get_observers().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "E_OBSERVER: Observers cannot act")]
+    fn test_observer_cannot_confirm() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        add_observer(&mut c);
+        c.num_confirmations = 2;
+        let request_id = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("carol".to_string()),
+            1_000
+        ));
+        c.confirm(request_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_OBSERVER: Observers cannot act")]
+    fn test_observer_cannot_add_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        add_observer(&mut c);
+        testing_env!(context_with_account(
+            AccountId::new_unchecked("carol".to_string()),
+            1_000
+        ));
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    /// Sets up the multisig with 100ns new member cooldown and carol added at 1000, returns pending request id.
+    fn new_member_cooldown_setup(c: &mut MultiSigContract) -> RequestId {
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetNewMemberCooldown {
+                new_member_cooldown: U64(100),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_new_member_cooldown(), U64(100));
+        set_block_timestamp(1_000);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddMember {
+                member: MultisigMember::Account {
+                    account_id: AccountId::new_unchecked("carol".to_string()),
+                },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.num_confirmations = 2;
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        })
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_CONFIG: New member cooldown must be at most a year")]
+    fn test_new_member_cooldown_too_long() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetNewMemberCooldown {
+                new_member_cooldown: U64(MAX_NEW_MEMBER_COOLDOWN + 1),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+    }
+
+    fn confirm_as_carol_at(c: &mut MultiSigContract, request_id: RequestId, block_timestamp: u64) {
+        let mut context =
+            context_with_account(AccountId::new_unchecked("carol".to_string()), 1_000);
+        context.block_timestamp = block_timestamp;
+        testing_env!(context);
+        c.confirm(request_id);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_MEMBER_COOLDOWN: Member was added too recently to confirm requests"
+    )]
+    fn test_new_member_cooldown_blocks_confirm() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let request_id = new_member_cooldown_setup(&mut c);
+        confirm_as_carol_at(&mut c, request_id, 1_050);
+    }
+
+    #[test]
+    fn test_new_member_cooldown_elapsed() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let request_id = new_member_cooldown_setup(&mut c);
+        confirm_as_carol_at(&mut c, request_id, 1_100);
+        assert!(c.has_confirmed(
+            request_id,
+            MultisigMember::Account {
+                account_id: AccountId::new_unchecked("carol".to_string()),
+            }
+        ));
+    }
+
+    fn sweep_request(reserve: Balance) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::SweepTransfer {
+                receiver_id: bob(),
+                reserve: reserve.into(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_sweep_transfer() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(sweep_request(400));
+        // balance changed between creation and execution
+        testing_env!(context_with_account(bob(), 1_500));
+        c.confirm(request_id);
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == bob())
+            .unwrap();
+        assert_eq!(receipt.actions, vec![VmAction::Transfer { deposit: 1_100 }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INSUFFICIENT_BALANCE: Reserve exceeds the account balance")]
+    fn test_sweep_transfer_insufficient_balance() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(sweep_request(2_000));
+    }
+
+    #[test]
+    fn test_get_balance() {
+        let mut context = context_with_key(PublicKey::try_from(TEST_KEY.to_vec()).unwrap(), 1_000);
+        context.account_locked_balance = 300;
+        testing_env!(context);
+        let c = MultiSigContract::new(members(), 2);
+        assert_eq!(c.get_balance(), (U128(1_000), U128(300)));
+    }
+
+    #[test]
+    fn test_execution_started_event() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        // logged once the request is removed, so the id can't be confirmed again
+        assert!(c.requests.get(&request_id).is_none());
+        assert_eq!(
+            last_event(),
+            json!({
+                "standard": "multisig",
+                "version": "1.0.0",
+                "event": "request_execution_started",
+                "data": [{"request_id": request_id, "receiver_id": "bob", "num_actions": 1}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_account_ids_rejected() {
+        // Account ids are validated when a request is deserialized; the
+        // contract checks them again in case one was built unchecked.
+        let overlong = "a".repeat(65);
+        for (receiver_id, member_id) in [("", "bob"), ("alice", ""), ("alice", overlong.as_str())] {
+            let request = json!({
+                "receiver_id": receiver_id,
+                "actions": [{
+                    "type": "AddMember",
+                    "member": { "account_id": member_id },
+                }],
+            });
+            assert!(serde_json::from_value::<MultiSigRequest>(request).is_err());
+        }
+        assert!(env::is_valid_account_id(b"alice"));
+        assert!(!env::is_valid_account_id(b""));
+        assert!(!env::is_valid_account_id(overlong.as_bytes()));
+    }
+
+    fn replace_all_members_request(num_confirmations: u32) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::ReplaceAllMembers {
+                members: vec![
+                    MultisigMember::AccessKey {
+                        public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+                    },
+                    MultisigMember::Account { account_id: bob() },
+                    MultisigMember::AccessKey {
+                        public_key: PublicKey::try_from([vec![0], vec![1; 32]].concat()).unwrap(),
+                    },
+                ],
+                num_confirmations,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_replace_all_members() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let request_id = c.add_request_and_confirm(replace_all_members_request(3));
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        assert_eq!(c.get_num_confirmations(), 3);
+        assert_eq!(c.requests.len(), 0);
+        let members = member_list(&c);
+        assert_eq!(members.len(), 3);
+        assert!(!members.contains(&MultisigMember::Account {
+            account_id: alice()
+        }));
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| r.receiver_id == alice())
+            .unwrap();
+        assert_eq!(receipt.actions.len(), 2);
+        assert_eq!(
+            receipt.actions[0],
+            VmAction::DeleteKey {
+                public_key: "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                    .parse()
+                    .unwrap(),
+            }
+        );
+        match &receipt.actions[1] {
+            VmAction::AddKeyWithFunctionCall { public_key, .. } => {
+                assert_eq!(
+                    *public_key,
+                    PublicKey::try_from([vec![0], vec![1; 32]].concat()).unwrap()
+                )
+            }
+            action => panic!("Unexpected action {:?}", action),
+        }
+
+        // the new threshold applies to new requests
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.requests.len(), 1);
+    }
+
+    #[test]
+    fn test_replace_all_members_with_observer() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let observer_key = PublicKey::try_from([vec![0], vec![1; 32]].concat()).unwrap();
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddObserver {
+                member: MultisigMember::AccessKey {
+                    public_key: observer_key.clone(),
+                },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.add_request_and_confirm(replace_all_members_request(3));
+        assert!(c.get_observers().is_empty());
+        assert_eq!(c.num_voting_members(), 3);
+        assert!(get_created_receipts()
+            .iter()
+            .any(|receipt| receipt.actions.iter().any(|action| matches!(
+                action,
+                VmAction::AddKeyWithFunctionCall { public_key, .. } if public_key == &observer_key
+            ))));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Number of confirmations must be between 1 and the number of members"
+    )]
+    fn test_replace_all_members_above_members() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(replace_all_members_request(4));
+    }
+
+    #[test]
+    fn test_get_all_progress() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request = MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::Transfer {
+                amount: 1_000.into(),
+                memo: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let first = c.add_request_and_confirm(request.clone());
+        let second = c.add_request(request.clone());
+        let third = c.add_request_and_confirm(request);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(first);
+        assert_eq!(
+            c.get_all_progress(0, 10),
+            vec![(first, 2, 3), (second, 0, 3), (third, 1, 3)]
+        );
+        assert_eq!(c.get_all_progress(1, 1), vec![(second, 0, 3)]);
+    }
+
+    #[test]
+    fn test_decommission_contract() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000_000_000_000_000_000_000_000_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(transfer_request());
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DecommissionContract {
+                beneficiary_id: Some(bob()),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(
+            bob(),
+            1_000_000_000_000_000_000_000_000_000
+        ));
+        c.confirm(request_id);
+        assert!(c.get_members().is_empty());
+        assert!(c.list_request_ids().is_empty());
+        assert_eq!(c.get_num_requests_per_member(members()[3].clone()), 0);
+        let receipts = get_created_receipts();
+        let deleted_keys: Vec<&VmAction> = receipts
+            .iter()
+            .find(|r| r.receiver_id == alice())
+            .unwrap()
+            .actions
+            .iter()
+            .filter(|action| matches!(action, VmAction::DeleteKey { .. }))
+            .collect();
+        assert_eq!(deleted_keys.len(), 2);
+        let transfer = receipts.iter().find(|r| r.receiver_id == bob()).unwrap();
+        match transfer.actions[0] {
+            // the balance needed for the remaining storage stays
+            VmAction::Transfer { deposit } => {
+                assert!(deposit > 0 && deposit < 1_000_000_000_000_000_000_000_000_000)
+            }
+            ref action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    fn test_decommission_beneficiary() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000_000_000_000_000_000_000_000_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        assert_eq!(c.get_decommission_beneficiary(), None);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetDecommissionBeneficiary {
+                beneficiary_id: Some(bob()),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_decommission_beneficiary(), Some(bob()));
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DecommissionContract {
+                beneficiary_id: None,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.get_members().is_empty());
+        let receipts = get_created_receipts();
+        let transfer = receipts.iter().find(|r| r.receiver_id == bob()).unwrap();
+        match transfer.actions[0] {
+            VmAction::Transfer { deposit } => assert!(deposit > 0),
+            ref action => panic!("Unexpected action {:?}", action),
+        }
+    }
+
+    fn multi_transfer_request(transfers: Vec<(AccountId, u128)>) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::MultiTransfer {
+                transfers: transfers
+                    .into_iter()
+                    .map(|(account_id, amount)| (account_id, U128(amount)))
+                    .collect(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    fn test_multi_transfer() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let carol: AccountId = "carol".parse().unwrap();
+        c.add_request_and_confirm(multi_transfer_request(vec![
+            (bob(), 100),
+            (carol.clone(), 200),
+        ]));
+        let receipts = get_created_receipts();
+        for (account_id, amount) in [(bob(), 100), (carol, 200)] {
+            let receipt = receipts
+                .iter()
+                .find(|r| r.receiver_id == account_id)
+                .unwrap();
+            match receipt.actions[..] {
+                [VmAction::Transfer { deposit }] => assert_eq!(deposit, amount),
+                ref actions => panic!("Unexpected actions {:?}", actions),
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_ARGUMENT: MultiTransfer must have between 1 and 32 transfers"
+    )]
+    fn test_multi_transfer_over_cap() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request(multi_transfer_request(vec![
+            (bob(), 1);
+            MAX_MULTI_TRANSFER_RECIPIENTS
+                + 1
+        ]));
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: MultiTransfer amounts must be positive")]
+    fn test_multi_transfer_zero_amount() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request(multi_transfer_request(vec![(bob(), 1), (alice(), 0)]));
+    }
+
+    #[test]
+    fn test_get_pending_action_kinds() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let transfer = c.add_request(transfer_request());
+        let upgrade = c.add_request(MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![
+                MultiSigRequestAction::AddKey {
+                    public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+                    permission: None,
+                },
+                MultiSigRequestAction::DeployContract {
+                    code: vec![1, 2, 3].into(),
+                },
+            ],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(
+            c.get_pending_action_kinds(0, 10),
+            vec![
+                (transfer, vec!["Transfer".to_string()]),
+                (
+                    upgrade,
+                    vec!["AddKey".to_string(), "DeployContract".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(c.get_pending_action_kinds(1, 10).len(), 1);
+    }
+
+    fn confirmation_percentage_request(confirmation_percentage: Option<u8>) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetConfirmationPercentage {
+                confirmation_percentage,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Removing given members will leave no voting members"
+    )]
+    fn test_confirmation_percentage_delete_all_members() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(confirmation_percentage_request(Some(50)));
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: members()
+                .into_iter()
+                .map(|member| MultiSigRequestAction::DeleteMember { member })
+                .collect(),
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+    }
+
+    #[test]
+    fn test_confirmation_percentage() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(confirmation_percentage_request(Some(50)));
+        assert_eq!(c.get_confirmation_percentage(), Some(50));
+        // 50% of 4 members
+        assert_eq!(c.get_required_confirmations(), 2);
+        let request_id = c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddMember {
+                member: MultisigMember::Account {
+                    account_id: "carol".parse().unwrap(),
+                },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_effective_threshold(request_id), 2);
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        // 50% of 5 members, rounded up
+        assert_eq!(c.get_required_confirmations(), 3);
+        assert_eq!(c.get_num_confirmations(), 1);
+
+        let request_id = c.add_request_and_confirm(confirmation_percentage_request(None));
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert!(c.requests.get(&request_id).is_some());
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.get_confirmation_percentage(), None);
+        assert_eq!(c.get_required_confirmations(), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Confirmation percentage must be between 1 and 100"
+    )]
+    fn test_confirmation_percentage_zero() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(confirmation_percentage_request(Some(0)));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_INVALID_CONFIG: Confirmation percentage must be between 1 and 100"
+    )]
+    fn test_confirmation_percentage_over_100() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(confirmation_percentage_request(Some(101)));
+    }
+
+    #[test]
+    fn test_get_request_for_confirmation() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 3);
+        let request_id = c.add_request_and_confirm(transfer_request());
+        let (request, confirmations, required) = c.get_request_for_confirmation(request_id);
+        assert_eq!(request, transfer_request());
+        assert_eq!((confirmations, required), (1, 3));
+
+        // bob is a contract confirming through a cross-contract call signed by its own user
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(bob())
+            .signer_account_id("dao-user".parse().unwrap())
+            .account_balance(1_000)
+            .build());
+        c.confirm(request_id);
+        let (_, confirmations, required) = c.get_request_for_confirmation(request_id);
+        assert_eq!((confirmations, required), (2, 3));
+        assert!(c
+            .get_confirmations(request_id)
+            .contains(&MultisigMember::Account { account_id: bob() }.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "No such request")]
+    fn test_get_request_for_confirmation_unknown() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let c = MultiSigContract::new(members(), 2);
+        c.get_request_for_confirmation(0);
+    }
+
+    #[test]
+    fn test_export_state() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(transfer_request());
+        let confirmed = c.add_request_and_confirm(transfer_request());
+        c.add_request(transfer_request());
+
+        let snapshot = c.export_state(1, 1);
+        assert_eq!(snapshot.members, member_list(&c));
+        assert_eq!(snapshot.num_confirmations, 2);
+        assert_eq!(snapshot.request_nonce, 3);
+        assert_eq!(snapshot.active_requests_limit, ACTIVE_REQUESTS_LIMIT);
+        assert_eq!(snapshot.num_requests, 3);
+        assert_eq!(
+            snapshot.requests,
+            vec![RequestSnapshot {
+                request_id: confirmed,
+                member: members()[3].clone(),
+                added_timestamp: U64(100),
+                request: transfer_request(),
+                confirmations: vec![(members()[3].to_string(), U64(100))],
+            }]
+        );
+        assert!(c.export_state(3, 10).requests.is_empty());
+    }
+
+    fn independent_confirmation_setup() -> MultiSigContract {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetRequireIndependentConfirmation {
+                require_independent_confirmation: true,
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert!(c.get_require_independent_confirmation());
+        c.num_confirmations = 2;
+        c
+    }
+
+    fn delete_bob_request() -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteMember {
+                member: MultisigMember::Account { account_id: bob() },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    /// Adds `first` and `second`, then executes `first` with confirmations of two key members.
+    fn execute_first_of(
+        c: &mut MultiSigContract,
+        first: MultiSigRequest,
+        second: MultiSigRequest,
+    ) -> RequestId {
+        let first_id = c.add_request_and_confirm(first);
+        let second_id = c.add_request_and_confirm(second);
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            1_000
+        ));
+        c.confirm(first_id);
+        assert!(c.requests.get(&first_id).is_none());
+        second_id
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "E_MEMBERS_CHANGED: Members changed since the request was added, it has to be added again"
+    )]
+    fn test_members_changed_before_execution() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = execute_first_of(&mut c, delete_bob_request(), add_carol_request());
+        assert_eq!(c.get_members().len(), 3);
+        c.confirm(request_id);
     }
 
-    pub fn get_num_requests_per_member(&self, member: MultisigMember) -> u32 {
-        self.num_requests_pk.get(&member.to_string()).unwrap_or(0)
+    #[test]
+    #[should_panic(
+        expected = "E_MEMBERS_CHANGED: Members changed since the request was added, it has to be added again"
+    )]
+    fn test_members_replaced_before_execution() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = execute_first_of(
+            &mut c,
+            membership_batch(vec![bob()], vec!["carol"]),
+            membership_batch(vec![], vec!["dave"]),
+        );
+        // same number of members, but not the same ones
+        assert_eq!(c.get_members().len(), members().len());
+        c.confirm(request_id);
     }
 
-    pub fn list_request_ids(&self) -> Vec<RequestId> {
-        self.requests.keys().collect()
+    #[test]
+    fn test_observer_added_before_execution() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let add_observer = MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddObserver {
+                member: MultisigMember::Account {
+                    account_id: "carol".parse().unwrap(),
+                },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        };
+        let request_id =
+            execute_first_of(&mut c, add_observer, membership_batch(vec![], vec!["dave"]));
+        c.confirm(request_id);
+        assert!(c.requests.get(&request_id).is_none());
     }
 
-    pub fn get_confirmations(&self, request_id: RequestId) -> Vec<String> {
-        self.confirmations
-            .get(&request_id)
-            .unwrap_or_else(|| env::panic_str("No such request"))
-            .into_iter()
-            .collect()
+    #[test]
+    fn test_members_changed_other_request_executes() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = execute_first_of(&mut c, delete_bob_request(), transfer_request());
+        c.confirm(request_id);
+        assert!(c.requests.get(&request_id).is_none());
     }
 
-    pub fn get_num_confirmations(&self) -> u32 {
-        self.num_confirmations
+    fn add_carol_request() -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddMember {
+                member: MultisigMember::Account {
+                    account_id: "carol".parse().unwrap(),
+                },
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
     }
 
-    pub fn get_request_nonce(&self) -> u32 {
-        self.request_nonce
+    #[test]
+    fn test_require_independent_confirmation() {
+        let mut c = independent_confirmation_setup();
+        // only self-modifying requests are affected
+        c.add_request_and_confirm(transfer_request());
+        let request_id = c.add_request(add_carol_request());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(request_id);
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        c.confirm(request_id);
+        assert_eq!(c.get_members().len(), 5);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::convert::TryFrom;
-    use std::fmt::{Debug, Error, Formatter};
 
-    use near_sdk::test_utils::VMContextBuilder;
-    use near_sdk::Balance;
-    use near_sdk::{testing_env, PublicKey};
-    use near_sdk::{AccountId, VMContext};
-
-    use super::*;
-
-    /// Used for asserts_eq.
-    /// TODO: replace with derive when https://github.com/near/near-sdk-rs/issues/165
-    impl Debug for MultiSigRequest {
-        fn fmt(&self, _f: &mut Formatter<'_>) -> Result<(), Error> {
-            panic!("Should not trigger");
-        }
+    #[test]
+    #[should_panic(expected = "E_NOT_INDEPENDENT: Another member must confirm this request first")]
+    fn test_require_independent_confirmation_by_creator() {
+        let mut c = independent_confirmation_setup();
+        let request_id = c.add_request(add_carol_request());
+        c.confirm(request_id);
     }
 
-    pub fn alice() -> AccountId {
-        AccountId::new_unchecked("alice".to_string())
-    }
-    pub fn bob() -> AccountId {
-        AccountId::new_unchecked("bob".to_string())
+    #[test]
+    fn test_normalize_member() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 2);
+        assert_eq!(
+            c.normalize_member(members()[2].clone()),
+            r#"{"public_key":"ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"}"#
+        );
+        assert_eq!(
+            c.normalize_member(MultisigMember::Account { account_id: bob() }),
+            r#"{"account_id":"bob"}"#
+        );
+        let request_id = c.add_request_and_confirm(transfer_request());
+        assert_eq!(
+            c.get_confirmations(request_id),
+            vec![c.normalize_member(members()[3].clone())]
+        );
     }
 
-    const TEST_KEY: [u8; 33] = [
-        0, 247, 230, 176, 93, 224, 175, 33, 211, 72, 124, 12, 163, 219, 7, 137, 3, 37, 162, 199,
-        181, 38, 90, 244, 111, 207, 37, 216, 79, 84, 50, 83, 164,
-    ];
+    #[test]
+    fn test_confirm_and_prune() {
+        set_block_timestamp(0);
+        let mut c = MultiSigContract::new(members(), 2);
+        let stale = c.add_request(transfer_request());
+        let other_stale = c.add_request(transfer_request());
+        let request_id = c.add_request_and_confirm(transfer_request());
+        set_block_timestamp(REQUEST_COOLDOWN + 1);
+        let fresh = c.add_request(transfer_request());
 
-    fn members() -> Vec<MultisigMember> {
-        vec![
-            MultisigMember::Account {
-                account_id: alice(),
-            },
-            MultisigMember::Account { account_id: bob() },
-            MultisigMember::AccessKey {
-                public_key: PublicKey::from(
-                    "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
-                        .parse()
-                        .unwrap(),
-                ),
-            },
-            MultisigMember::AccessKey {
-                public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            },
-        ]
+        let mut context = context_with_account(bob(), 1_000);
+        context.block_timestamp = REQUEST_COOLDOWN + 1;
+        testing_env!(context);
+        c.confirm_and_prune(request_id, vec![stale, other_stale, fresh, request_id, 99]);
+        // the confirmed request executed, and only the fresh one is still in its cooldown
+        assert_eq!(c.list_request_ids(), vec![fresh]);
+        assert_eq!(c.get_num_requests_per_member(members()[3].clone()), 1);
     }
 
-    fn context_with_key(key: PublicKey, amount: Balance) -> VMContext {
-        context_with_account_key(alice(), key, amount)
+    fn max_total_requests_setup() -> MultiSigContract {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        assert_eq!(c.get_max_total_requests(), None);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetMaxTotalRequests {
+                max_total_requests: Some(2),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(c.get_max_total_requests(), Some(2));
+        c.add_request(transfer_request());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.add_request(transfer_request());
+        c
     }
 
-    fn context_with_account(account_id: AccountId, amount: Balance) -> VMContext {
-        context_with_account_key(
-            account_id,
-            PublicKey::try_from(vec![
-                0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-                24, 25, 26, 27, 28, 29, 30, 31, 32, 33,
-            ])
-            .unwrap(),
-            amount,
-        )
+    #[test]
+    fn test_max_total_requests() {
+        let mut c = max_total_requests_setup();
+        // executing a request frees up room
+        let request_id = c.list_request_ids()[0];
+        c.confirm(request_id);
+        c.add_request(transfer_request());
+        assert_eq!(c.requests.len(), 2);
     }
 
-    fn context_with_account_key(
-        account_id: AccountId,
-        key: PublicKey,
-        amount: Balance,
-    ) -> VMContext {
-        VMContextBuilder::new()
-            .current_account_id(alice())
-            .predecessor_account_id(account_id.clone())
-            .signer_account_id(account_id.clone())
-            .signer_account_pk(key)
-            .account_balance(amount)
-            .build()
+    #[test]
+    #[should_panic(expected = "E_TOO_MANY_REQUESTS: Global request limit reached")]
+    fn test_max_total_requests_reached() {
+        let mut c = max_total_requests_setup();
+        testing_env!(context_with_key(
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
+                .parse()
+                .unwrap(),
+            1_000
+        ));
+        c.add_request(transfer_request());
     }
 
-    fn context_with_key_future(key: PublicKey, amount: Balance) -> VMContext {
-        VMContextBuilder::new()
-            .current_account_id(alice())
-            .block_timestamp(REQUEST_COOLDOWN + 1)
-            .predecessor_account_id(alice())
-            .signer_account_id(alice())
-            .signer_account_pk(key)
-            .account_balance(amount)
-            .build()
+    #[test]
+    fn test_refresh_key_allowances() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::RefreshKeyAllowances],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let receipt = get_created_receipts()
+            .into_iter()
+            .find(|r| {
+                r.actions
+                    .iter()
+                    .any(|action| matches!(action, VmAction::DeleteKey { .. }))
+            })
+            .unwrap();
+        let key_members: Vec<PublicKey> = members()
+            .into_iter()
+            .filter_map(|member| match member {
+                MultisigMember::AccessKey { public_key } => Some(public_key),
+                MultisigMember::Account { .. } => None,
+            })
+            .collect();
+        assert_eq!(receipt.actions.len(), 2 * key_members.len());
+        for pair in receipt.actions.chunks(2) {
+            match pair {
+                [VmAction::DeleteKey {
+                    public_key: deleted,
+                }, VmAction::AddKeyWithFunctionCall {
+                    public_key: added,
+                    method_names,
+                    ..
+                }] => {
+                    assert_eq!(deleted, added);
+                    assert!(key_members.contains(added));
+                    assert!(method_names.contains(&"confirm".to_string()));
+                }
+                _ => panic!("Unexpected actions {:?}", pair),
+            }
+        }
     }
 
     #[test]
-    fn test_multi_3_of_n() {
-        let amount = 1_000;
+    fn test_get_member_allowances() {
         testing_env!(context_with_key(
-            PublicKey::from(
-                "Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
-                    .parse()
-                    .unwrap()
-            ),
-            amount
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        let request = MultiSigRequest {
-            receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::Transfer {
-                amount: amount.into(),
+        let mut c = MultiSigContract::new(members(), 1);
+        let mut allowances = c.get_member_allowances();
+        allowances.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut expected = vec![
+            (members()[2].to_string(), U128(DEFAULT_ALLOWANCE)),
+            (members()[3].to_string(), U128(DEFAULT_ALLOWANCE)),
+        ];
+        expected.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(allowances, expected);
+
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::DeleteMember {
+                member: members()[2].clone(),
             }],
-        };
-        let request_id = c.add_request(request.clone());
-        assert_eq!(c.get_request(request_id), request);
-        assert_eq!(c.list_request_ids(), vec![request_id]);
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        assert_eq!(
+            c.get_member_allowances(),
+            vec![(members()[3].to_string(), U128(DEFAULT_ALLOWANCE))]
+        );
+    }
+
+    #[test]
+    fn test_single_confirmation_stores_no_confirmations() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let request_id = c.add_request(transfer_request());
+        assert!(c.confirmations.get(&request_id).is_none());
+        assert!(c.get_confirmations(request_id).is_empty());
+        assert_eq!(c.get_all_progress(0, 10), vec![(request_id, 0, 1)]);
         c.confirm(request_id);
-        assert_eq!(c.requests.len(), 1);
-        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
+        assert!(c.requests.get(&request_id).is_none());
+
         testing_env!(context_with_key(
-            PublicKey::from(
-                "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
-                    .parse()
-                    .unwrap()
-            ),
-            amount
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
+        ));
+        let request_id = c.add_request_and_confirm(transfer_request());
+        assert!(c.requests.get(&request_id).is_none());
+        assert!(c.confirmations.get(&request_id).is_none());
+        assert!(c.check_invariants(0, 100).is_empty());
+    }
+
+    #[test]
+    fn test_threshold_raised_after_single_confirmation_request() {
+        testing_env!(context_with_key(
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
         ));
+        let mut c = MultiSigContract::new(members(), 1);
+        let request_id = c.add_request(transfer_request());
+        c.num_confirmations = 2;
         c.confirm(request_id);
-        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 2);
-        assert_eq!(c.get_confirmations(request_id).len(), 2);
-        testing_env!(context_with_account(bob(), amount));
+        assert_eq!(c.get_confirmations(request_id).len(), 1);
+        testing_env!(context_with_account(bob(), 1_000));
         c.confirm(request_id);
-        // TODO: confirm that funds were transferred out via promise.
-        assert_eq!(c.requests.len(), 0);
+        assert!(c.requests.get(&request_id).is_none());
     }
 
     #[test]
-    fn test_multi_add_request_and_confirm() {
-        let amount = 1_000;
+    fn test_reaches_threshold_boundaries() {
+        assert!(!reaches_threshold(0, 0, 1));
+        assert!(reaches_threshold(0, 1, 1));
+        assert!(!reaches_threshold(u32::MAX as usize - 2, 1, u32::MAX));
+        assert!(reaches_threshold(u32::MAX as usize - 1, 1, u32::MAX));
+        // a length past u32::MAX must not truncate to a small count
+        assert!(reaches_threshold(u32::MAX as usize + 2, 0, u32::MAX));
+        assert!(reaches_threshold(usize::MAX, u64::MAX, u32::MAX));
+    }
+
+    #[test]
+    fn test_num_requests_bookkeeping_saturates() {
         testing_env!(context_with_key(
-            PublicKey::from(
-                "Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
-                    .parse()
-                    .unwrap()
-            ),
-            amount
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        let request = MultiSigRequest {
-            receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::Transfer {
-                amount: amount.into(),
-            }],
+        let mut c = MultiSigContract::new(members(), 2);
+        let member = MultisigMember::AccessKey {
+            public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
         };
-        let request_id = c.add_request_and_confirm(request.clone());
-        assert_eq!(c.get_request(request_id), request);
-        assert_eq!(c.list_request_ids(), vec![request_id]);
-        // c.confirm(request_id);
-        assert_eq!(c.requests.len(), 1);
-        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
-        testing_env!(context_with_key(
-            PublicKey::from(
-                "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
-                    .parse()
-                    .unwrap()
-            ),
-            amount
-        ));
+        let request_id = c.add_request(transfer_request());
+        assert_eq!(c.get_num_requests_per_member(member.clone()), 1);
+        // out of sync counter must not wrap around when the request is removed
+        c.num_requests_pk.insert(&member.to_string(), &0);
+        c.delete_request(request_id);
+        assert_eq!(c.get_num_requests_per_member(member.clone()), 0);
+        c.active_requests_limit = u32::MAX;
+        c.num_requests_pk
+            .insert(&member.to_string(), &(u32::MAX - 1));
+        c.add_request(transfer_request());
+        assert_eq!(c.get_num_requests_per_member(member), u32::MAX);
+    }
+
+    #[test]
+    fn test_add_request_with_ttl() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_with_ttl(transfer_request(), U64(1_000));
+        assert_eq!(c.get_request_expires_at(request_id), Some(U64(1_100)));
+        let other_id = c.add_request(transfer_request());
+        assert_eq!(c.get_request_expires_at(other_id), None);
+        set_block_timestamp(1_099);
         c.confirm(request_id);
-        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 2);
-        assert_eq!(c.get_confirmations(request_id).len(), 2);
-        testing_env!(context_with_account(bob(), amount));
+        assert_eq!(c.get_confirmations(request_id).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_EXPIRED: Request expired")]
+    fn test_add_request_with_ttl_expired() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        let request_id = c.add_request_with_ttl(transfer_request(), U64(1_000));
+        set_block_timestamp(1_100);
         c.confirm(request_id);
-        // TODO: confirm that funds were transferred out via promise.
-        assert_eq!(c.requests.len(), 0);
     }
 
     #[test]
-    fn add_key_delete_key_storage_cleared() {
-        let amount = 1_000;
+    #[should_panic(
+        expected = "E_INVALID_ARGUMENT: Request TTL must be positive and at most a year"
+    )]
+    fn test_add_request_with_ttl_too_long() {
+        set_block_timestamp(100);
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request_with_ttl(transfer_request(), U64(MAX_REQUEST_TTL + 1));
+    }
+
+    #[test]
+    fn test_estimate_execution_gas() {
         testing_env!(context_with_key(
-            PublicKey::from(
-                "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
-                    .parse()
-                    .unwrap()
-            ),
-            amount
-        ));
-        let mut c = MultiSigContract::new(members(), 1);
-        let new_key: PublicKey = PublicKey::from(
-            "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
+            "ed25519:Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy"
                 .parse()
                 .unwrap(),
-        );
-        // vm current_account_id is alice, receiver_id must be alice
-        let request = MultiSigRequest {
-            receiver_id: alice(),
-            actions: vec![MultiSigRequestAction::AddKey {
-                public_key: new_key.clone(),
-                permission: None,
-            }],
-        };
-        // make request
-        c.add_request_and_confirm(request.clone());
-        // should be empty now
-        assert_eq!(c.requests.len(), 0);
-        // switch accounts
-        testing_env!(context_with_key(
-            PublicKey::from(
-                "HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"
-                    .parse()
-                    .unwrap()
-            ),
-            amount
+            1_000
         ));
-        let request2 = MultiSigRequest {
+        let mut c = MultiSigContract::new(members(), 2);
+        let one_id = c.add_request(transfer_request());
+        let mut request = transfer_request();
+        request.actions.push(MultiSigRequestAction::Transfer {
+            amount: U128(500),
+            memo: None,
+        });
+        let two_id = c.add_request(request);
+        let call_id = c.add_request(MultiSigRequest {
             receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::FunctionCall {
+                method_name: "foo".to_string(),
+                args: Base64VecU8(vec![]),
+                deposit: U128(0),
+                gas: U64(50_000_000_000_000),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        let one = c.estimate_execution_gas(one_id).0;
+        let two = c.estimate_execution_gas(two_id).0;
+        assert_eq!(one, EXECUTION_BASE_GAS.0 + ON_EXECUTE_GAS.0 + ACTION_GAS.0);
+        assert_eq!(two - one, ACTION_GAS.0);
+        assert_eq!(
+            c.estimate_execution_gas(call_id).0,
+            EXECUTION_BASE_GAS.0 + ON_EXECUTE_GAS.0 + 50_000_000_000_000
+        );
+    }
+
+    fn memo_transfer_request(amount: u128, memo: Option<&str>) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: bob(),
             actions: vec![MultiSigRequestAction::Transfer {
                 amount: amount.into(),
+                memo: memo.map(|m| m.to_string()),
             }],
-        };
-        // make request but don't confirm
-        c.add_request(request2.clone());
-        // should have 1 request now
-        let new_member = MultisigMember::AccessKey {
-            public_key: new_key.clone(),
-        };
-        assert_eq!(c.requests.len(), 1);
-        assert_eq!(c.get_num_requests_per_member(new_member.clone()), 1);
-        // self delete key
-        let request3 = MultiSigRequest {
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    fn set_require_memo_above(c: &mut MultiSigContract, amount: u128) {
+        c.add_request_and_confirm(MultiSigRequest {
             receiver_id: alice(),
-            actions: vec![MultiSigRequestAction::DeleteMember {
-                member: new_member.clone(),
+            actions: vec![MultiSigRequestAction::SetRequireMemoAbove {
+                require_memo_above: Some(U128(amount)),
             }],
-        };
-        // make request and confirm
-        c.add_request_and_confirm(request3.clone());
-        // should be empty now
-        assert_eq!(c.requests.len(), 0);
-        assert_eq!(c.get_num_requests_per_member(new_member), 0);
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
     }
 
     #[test]
-    #[should_panic]
-    fn test_panics_add_key_different_account() {
-        let amount = 1_000;
+    fn test_require_memo_above() {
         testing_env!(context_with_key(
-            PublicKey::try_from(Vec::from("Eg2jtsiMrprn7zgKKUk79qM1hWhANsFyE6JSX4txLEuy")).unwrap(),
-            amount
+            PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 1);
-        let new_key: PublicKey =
-            PublicKey::try_from(Vec::from("HghiythFFPjVXwc9BLNi8uqFmfQc1DWFrJQ4nE6ANo7R"))
-                .unwrap()
-                .into();
-        // vm current_account_id is alice, receiver_id must be alice
-        let request = MultiSigRequest {
-            receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::AddKey {
-                public_key: new_key.clone(),
-                permission: None,
-            }],
-        };
-        // make request
-        c.add_request_and_confirm(request);
+        let mut c = MultiSigContract::new(members(), 2);
+        assert_eq!(c.get_require_memo_above(), None);
+        c.num_confirmations = 1;
+        set_require_memo_above(&mut c, 100);
+        c.num_confirmations = 2;
+        assert_eq!(c.get_require_memo_above(), Some(U128(100)));
+        c.add_request(memo_transfer_request(100, None));
+        c.add_request(memo_transfer_request(101, Some("payroll")));
+        assert_eq!(c.requests.len(), 2);
     }
 
     #[test]
-    fn test_change_num_confirmations() {
-        let amount = 1_000;
+    #[should_panic(
+        expected = "E_INVALID_ARGUMENT: Transfers above the memo threshold require a memo"
+    )]
+    fn test_require_memo_above_missing_memo() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
         let mut c = MultiSigContract::new(members(), 1);
-        let request_id = c.add_request(MultiSigRequest {
+        set_require_memo_above(&mut c, 100);
+        c.add_request(memo_transfer_request(101, Some("")));
+    }
+
+    fn deploy_request(code_size: usize) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: bob(),
+            actions: vec![MultiSigRequestAction::DeployContract {
+                code: vec![0; code_size].into(),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
+    }
+
+    fn set_max_contract_code_size(c: &mut MultiSigContract, max_contract_code_size: u64) {
+        c.add_request_and_confirm(MultiSigRequest {
             receiver_id: alice(),
-            actions: vec![MultiSigRequestAction::SetNumConfirmations {
-                num_confirmations: 2,
+            actions: vec![MultiSigRequestAction::SetMaxContractCodeSize {
+                max_contract_code_size: U64(max_contract_code_size),
             }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
         });
-        c.confirm(request_id);
-        assert_eq!(c.num_confirmations, 2);
+    }
+
+    fn add_function_call_key_request(
+        receiver_id: AccountId,
+        method_names: Vec<String>,
+    ) -> MultiSigRequest {
+        MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::AddKey {
+                public_key: PublicKey::try_from([vec![0], vec![1; 32]].concat()).unwrap(),
+                permission: Some(FunctionCallPermission {
+                    allowance: None,
+                    receiver_id,
+                    method_names,
+                }),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        }
     }
 
     #[test]
-    #[should_panic]
-    fn test_panics_on_second_confirm() {
-        let amount = 1_000;
+    fn test_add_function_call_key_method_names() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        let request_id = c.add_request(MultiSigRequest {
-            receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::Transfer {
-                amount: amount.into(),
-            }],
-        });
-        assert_eq!(c.requests.len(), 1);
-        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 0);
-        c.confirm(request_id);
-        assert_eq!(c.confirmations.get(&request_id).unwrap().len(), 1);
-        c.confirm(request_id);
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(add_function_call_key_request(
+            alice(),
+            vec!["confirm".to_string()],
+        ));
+        // keys for other contracts may call any method
+        c.add_request(add_function_call_key_request(bob(), vec![]));
+        assert_eq!(c.requests.len(), 2);
     }
 
     #[test]
-    #[should_panic]
-    fn test_panics_delete_request() {
-        let amount = 1_000;
+    #[should_panic(
+        expected = "E_INVALID_ARGUMENT: Function call keys for this contract must list their method names"
+    )]
+    fn test_add_function_call_key_no_method_names() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        let request_id = c.add_request(MultiSigRequest {
-            receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::Transfer {
-                amount: amount.into(),
-            }],
-        });
-        c.delete_request(request_id);
+        let mut c = MultiSigContract::new(members(), 2);
+        c.add_request(add_function_call_key_request(alice(), vec![]));
     }
 
     #[test]
-    fn test_delete_request_future() {
-        let amount = 1_000;
+    fn test_max_contract_code_size() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        let request_id = c.add_request(MultiSigRequest {
-            receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::Transfer {
-                amount: amount.into(),
-            }],
-        });
-        c.confirm(request_id);
-        testing_env!(context_with_key_future(
+        let mut c = MultiSigContract::new(members(), 2);
+        assert_eq!(
+            c.get_max_contract_code_size(),
+            U64(DEFAULT_MAX_CONTRACT_CODE_SIZE)
+        );
+        c.num_confirmations = 1;
+        set_max_contract_code_size(&mut c, 16);
+        c.num_confirmations = 2;
+        assert_eq!(c.get_max_contract_code_size(), U64(16));
+        c.add_request(deploy_request(16));
+        assert_eq!(c.requests.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Contract code too large")]
+    fn test_max_contract_code_size_exceeded() {
+        testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        c.delete_request(request_id);
-        assert_eq!(c.requests.len(), 0);
-        assert!(c.confirmations.get(&request_id).is_none());
+        let mut c = MultiSigContract::new(members(), 1);
+        set_max_contract_code_size(&mut c, 16);
+        c.add_request(deploy_request(17));
     }
 
     #[test]
-    #[should_panic]
-    fn test_delete_request_panic_wrong_key() {
-        let amount = 1_000;
+    #[should_panic(expected = "E_INVALID_ARGUMENT: Contract code too large")]
+    fn test_max_contract_code_size_hash_exceeded() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        let request_id = c.add_request(MultiSigRequest {
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request(MultiSigRequest {
             receiver_id: bob(),
-            actions: vec![MultiSigRequestAction::Transfer {
-                amount: amount.into(),
+            actions: vec![MultiSigRequestAction::DeployContractHash {
+                code_hash: vec![0; 32].into(),
+                code_size: U64(DEFAULT_MAX_CONTRACT_CODE_SIZE + 1),
             }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
         });
+    }
+
+    #[test]
+    fn test_get_confirmations_by_member() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        c.delete_request(request_id);
+        let mut c = MultiSigContract::new(members(), 3);
+        let first = c.add_request_and_confirm(transfer_request());
+        let second = c.add_request(transfer_request());
+        let third = c.add_request_and_confirm(transfer_request());
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(second);
+
+        let key_member = MultisigMember::AccessKey {
+            public_key: PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
+        };
+        assert_eq!(
+            c.get_confirmations_by_member(key_member.clone(), 0, 10),
+            vec![first, third]
+        );
+        assert_eq!(
+            c.get_confirmations_by_member(key_member, 1, 10),
+            vec![third]
+        );
+        assert_eq!(
+            c.get_confirmations_by_member(MultisigMember::Account { account_id: bob() }, 0, 10),
+            vec![second]
+        );
+        assert!(c
+            .get_confirmations_by_member(
+                MultisigMember::Account {
+                    account_id: alice()
+                },
+                0,
+                10
+            )
+            .is_empty());
+    }
+
+    fn auto_prune_setup(prune_after: u64) -> MultiSigContract {
+        set_block_timestamp(0);
+        let mut c = MultiSigContract::new(members(), 1);
+        c.add_request_and_confirm(MultiSigRequest {
+            receiver_id: alice(),
+            actions: vec![MultiSigRequestAction::SetAutoPrune {
+                auto_prune: true,
+                prune_after: U64(prune_after),
+            }],
+            tag: None,
+            not_before: None,
+            min_confirmations_override: None,
+        });
+        c.num_confirmations = 2;
+        c
     }
 
     #[test]
-    #[should_panic]
-    fn test_too_many_requests() {
-        let amount = 1_000;
+    fn test_auto_prune() {
+        let mut c = auto_prune_setup(2 * REQUEST_COOLDOWN);
+        assert!(c.get_auto_prune());
+        assert_eq!(c.get_prune_after(), U64(2 * REQUEST_COOLDOWN));
+        let old = c.add_request(transfer_request());
+        set_block_timestamp(REQUEST_COOLDOWN + 1);
+        let newer = c.add_request(transfer_request());
+        // past the cooldown but not yet expired
+        assert!(c.requests.get(&old).is_some());
+
+        set_block_timestamp(2 * REQUEST_COOLDOWN + 1);
+        let newest = c.add_request(transfer_request());
+        assert!(c.requests.get(&old).is_none());
+        assert_eq!(last_event()["event"], "request_pruned");
+        assert_eq!(last_event()["data"][0]["request_id"], old);
+        let mut request_ids = c.list_request_ids();
+        request_ids.sort_unstable();
+        assert_eq!(request_ids, vec![newer, newest]);
+        assert_eq!(
+            c.get_num_requests_per_member(c.current_member().unwrap()),
+            2
+        );
+    }
+
+    #[test]
+    fn test_auto_prune_waits_for_cooldown() {
+        let mut c = auto_prune_setup(0);
+        let old = c.add_request(transfer_request());
+        set_block_timestamp(REQUEST_COOLDOWN);
+        c.add_request(transfer_request());
+        assert!(c.requests.get(&old).is_some());
+        set_block_timestamp(REQUEST_COOLDOWN + 1);
+        c.add_request(transfer_request());
+        assert!(c.requests.get(&old).is_none());
+    }
+
+    #[test]
+    fn test_storage_keys_distinct() {
+        let prefixes: Vec<Vec<u8>> = vec![
+            StorageKeys::Members,
+            StorageKeys::Requests,
+            StorageKeys::Confirmations,
+            StorageKeys::NumRequestsPk,
+            StorageKeys::ClientNonces,
+            StorageKeys::LastRequestTimestamp,
+            StorageKeys::Committees,
+            StorageKeys::CommitteeConfirmations,
+            StorageKeys::Observers,
+            StorageKeys::MemberAddedTimestamp,
+            StorageKeys::ExecutingRequests,
+            StorageKeys::SignedConfirmations,
+            StorageKeys::AccountMigrations,
+            StorageKeys::MemberAllowances,
+            StorageKeys::ActionKindCooldowns,
+            StorageKeys::ActionKindLastAdded,
+            StorageKeys::MemberReceivers,
+            StorageKeys::MemberWeights,
+            StorageKeys::RequestNotes,
+            StorageKeys::MemberActivity,
+        ]
+        .iter()
+        .map(|key| key.try_to_vec().unwrap())
+        .collect();
+        for (i, a) in prefixes.iter().enumerate() {
+            for b in prefixes.iter().skip(i + 1) {
+                assert!(!a.starts_with(b) && !b.starts_with(a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_storage_collections_independent() {
         testing_env!(context_with_key(
             PublicKey::try_from(TEST_KEY.to_vec()).unwrap(),
-            amount
+            1_000
         ));
-        let mut c = MultiSigContract::new(members(), 3);
-        for _i in 0..16 {
-            c.add_request(MultiSigRequest {
-                receiver_id: bob(),
-                actions: vec![MultiSigRequestAction::Transfer {
-                    amount: amount.into(),
-                }],
-            });
+        let mut c = MultiSigContract::new(members(), 2);
+        // the same key in every collection must not clash
+        let key = "key".to_string();
+        let member = MultisigMember::Account {
+            account_id: "carol".parse().unwrap(),
+        };
+        let request_id = c.add_request_and_confirm(transfer_request());
+        let request_with_signer = c.requests.get(&request_id).unwrap();
+        c.observers.insert(&member);
+        c.num_requests_pk.insert(&key, &1);
+        c.client_nonces.insert(&key, &vec![(key.clone(), 2)]);
+        c.last_request_timestamp.insert(&key, &3);
+        c.member_added_timestamp.insert(&key, &4);
+        c.committees.insert(
+            &key,
+            &Committee {
+                members: vec![member.clone()],
+                threshold: 5,
+            },
+        );
+        c.signed_confirmations.insert(&key, &(6, vec![]));
+        c.committee_confirmations
+            .insert(&request_id, &HashMap::new());
+        c.executing_requests
+            .insert(&request_id, &request_with_signer);
+        c.account_migrations.insert(&bob(), &alice());
+        c.member_allowances.insert(&key, &7);
+        c.action_kind_cooldowns.insert(&key, &8);
+        c.action_kind_last_added.insert(&key, &9);
+        c.member_receivers
+            .insert(&key, &vec![bob()].into_iter().collect());
+        c.member_weights.insert(&key, &10);
+        c.request_notes
+            .insert(&request_id, &vec![(key.clone(), key.clone())]);
+        c.member_activity.insert(&(key.clone(), key.clone()), &11);
+
+        assert_eq!(c.members.len(), members().len() as u64);
+        assert!(!c.members.contains(&member));
+        assert_eq!(c.observers.len(), 1);
+        assert_eq!(c.requests.len(), 1);
+        assert_eq!(c.get_confirmations(request_id).len(), 1);
+        assert_eq!(c.num_requests_pk.get(&key), Some(1));
+        assert_eq!(c.client_nonces.get(&key), Some(vec![(key.clone(), 2)]));
+        assert_eq!(c.last_request_timestamp.get(&key), Some(3));
+        assert_eq!(c.member_added_timestamp.get(&key), Some(4));
+        assert_eq!(c.committees.get(&key).unwrap().threshold, 5);
+        assert_eq!(c.signed_confirmations.get(&key), Some((6, vec![])));
+        assert!(c
+            .committee_confirmations
+            .get(&request_id)
+            .unwrap()
+            .is_empty());
+        assert!(c.executing_requests.get(&request_id).is_some());
+        assert_eq!(c.account_migrations.get(&bob()), Some(alice()));
+        assert_eq!(c.member_allowances.get(&key), Some(7));
+        assert_eq!(c.action_kind_cooldowns.get(&key), Some(8));
+        assert_eq!(c.action_kind_last_added.get(&key), Some(9));
+        assert!(c.member_receivers.get(&key).unwrap().contains(&bob()));
+        assert_eq!(c.member_weights.get(&key), Some(10));
+        assert_eq!(
+            c.get_request_notes(request_id),
+            vec![(key.clone(), key.clone())]
+        );
+        assert_eq!(c.member_activity.get(&(key.clone(), key)), Some(11));
+    }
+
+    #[test]
+    fn test_migrate_baseline_state() {
+        testing_env!(context_with_account(alice(), 1_000));
+        let member = members()[3].clone();
+        let mut old = OldMultiSigContract {
+            members: UnorderedSet::new(StorageKeys::Members),
+            num_confirmations: 2,
+            request_nonce: 8,
+            requests: UnorderedMap::new(StorageKeys::Requests),
+            confirmations: LookupMap::new(StorageKeys::Confirmations),
+            num_requests_pk: LookupMap::new(StorageKeys::NumRequestsPk),
+            active_requests_limit: 5,
+        };
+        for member in members() {
+            old.members.insert(&member);
         }
+        old.requests.insert(
+            &7,
+            &OldMultiSigRequestWithSigner {
+                request: OldMultiSigRequest {
+                    receiver_id: bob(),
+                    actions: vec![OldMultiSigRequestAction::Transfer {
+                        amount: 1_000.into(),
+                    }],
+                },
+                member: member.clone(),
+                added_timestamp: 0,
+            },
+        );
+        old.confirmations
+            .insert(&7, &vec![member.to_string()].into_iter().collect());
+        old.num_requests_pk.insert(&member.to_string(), &1);
+        env::state_write(&old);
+
+        let mut c = MultiSigContract::migrate();
+        assert_eq!(member_list(&c), members());
+        assert_eq!(c.get_num_confirmations(), 2);
+        assert_eq!(c.get_active_requests_limit(), 5);
+        assert_eq!(c.get_request_nonce(), 8);
+        assert_eq!(c.list_request_ids(), vec![7]);
+        assert!(c.get_request(7) == transfer_request());
+        assert_eq!(c.get_confirmations(7), vec![member.to_string()]);
+        assert_eq!(
+            c.confirmations.get(&7).unwrap().get(&member.to_string()),
+            Some(&env::block_timestamp())
+        );
+        assert_eq!(c.get_num_requests_per_member(member), 1);
+
+        // the migrated confirmation counts towards executing the request
+        testing_env!(context_with_account(bob(), 1_000));
+        c.confirm(7);
+        assert_eq!(c.list_request_ids(), Vec::<RequestId>::new());
+        assert_eq!(c.add_request(transfer_request()), 8);
+    }
+
+    #[test]
+    fn test_migrate_transfer_without_memo() {
+        // transfers stored by older versions have no memo
+        let stored = [vec![0], 1_000u128.to_le_bytes().to_vec()].concat();
+        let action: MultiSigRequestAction = OldMultiSigRequestAction::try_from_slice(&stored)
+            .unwrap()
+            .into();
+        let expected = MultiSigRequestAction::Transfer {
+            amount: 1_000.into(),
+            memo: None,
+        };
+        assert!(action == expected);
+        assert_eq!(expected.try_to_vec().unwrap(), [stored, vec![0]].concat());
     }
 
     #[test]